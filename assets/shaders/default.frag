@@ -0,0 +1,26 @@
+#version 150
+
+uniform vec4 color;
+uniform vec3 specular;
+uniform float shininess;
+uniform bool use_texture;
+uniform sampler2D diffuse_texture;
+
+in vec3 normal;
+in vec3 view_position;
+in vec2 uv;
+
+out vec4 out_color;
+
+void main(void) {
+    const vec3 vertex_to_light = normalize(vec3(1.0, 1.0, 0.0));
+
+    vec3 unit_normal = normalize(normal);
+    float diffuse = clamp(pow(dot(unit_normal, vertex_to_light), 3), 0.0, 0.7) + 0.3;
+    vec3 base_color = use_texture ? color.xyz * texture(diffuse_texture, uv).rgb : color.xyz;
+
+    vec3 half_vector = normalize(vertex_to_light - normalize(view_position));
+    float highlight = shininess > 0.0 ? pow(max(dot(unit_normal, half_vector), 0.0), shininess) : 0.0;
+
+    out_color = vec4(base_color * diffuse + specular * highlight, color.w);
+}
@@ -0,0 +1,22 @@
+#version 150
+
+uniform mat4 model_matrix;
+uniform mat4 view_matrix;
+uniform mat4 projection_matrix;
+uniform mat3 normal_matrix;
+
+in vec3 vertex_pos;
+in vec3 vertex_norm;
+in vec2 vertex_uv;
+
+out vec3 normal;
+out vec3 view_position;
+out vec2 uv;
+
+void main(void) {
+    vec4 position = view_matrix * model_matrix * vec4(vertex_pos, 1.0);
+    gl_Position = projection_matrix * position;
+    view_position = position.xyz;
+    normal = normalize(normal_matrix * vertex_norm);
+    uv = vertex_uv;
+}
@@ -0,0 +1,21 @@
+#!/bin/sh
+# Runs the rendering regression tests with Mesa's software rasterizer, as on
+# the build server, so that recorded reference images match it. Pass
+# --update to record missing references or to replace them.
+set -e
+
+if [ "$1" = "--update" ]; then
+    export UPDATE_GOLDEN=1
+fi
+
+export LIBGL_ALWAYS_SOFTWARE=1
+export MESA_GL_VERSION_OVERRIDE=3.2
+export MESA_GLSL_VERSION_OVERRIDE=150
+# GLFW must only be used from one thread
+export RUST_TEST_TASKS=1
+
+if [ -z "$DISPLAY" ]; then
+    exec xvfb-run -a -s "-screen 0 1024x768x24" cargo test -- --ignored
+fi
+
+exec cargo test -- --ignored
@@ -2,70 +2,234 @@ extern crate gl;
 
 use graphics;
 use std::mem;
-use gl::types::{ GLfloat, GLuint };
+use std::ptr;
+use std::rc::Rc;
+use std::cell::{ Cell, Ref, RefCell };
+use gl::types::{ GLenum, GLuint };
 
-pub struct Asset<'a> {
-    vertex_buffer_id: GLuint,
-    element_buffer_id: GLuint,
-    normal_buffer_id: GLuint,
-    num_vertices: usize,
-    num_indices: usize,
-    num_normals: usize,
+/// An OpenGL buffer object, the GPU memory is freed when it is dropped.
+pub struct Buffer {
+    id: GLuint,
+    target: GLenum,
+    length: Cell<usize>,
 }
 
-impl<'a> Asset<'a> {
-    pub fn new_from_file(filepath: &str) -> Asset<'a> {
-        let (vertices, normals, indices) = graphics::utils::import_from_obj(filepath);
-        let mut vertex_buffer_id: GLuint = 0;
-        let mut element_buffer_id: GLuint = 0;
-        let mut normal_buffer_id: GLuint = 0;
+impl Buffer {
+    /// Creates a buffer object and sends the data to the GPU.
+    pub fn new<T>(target: GLenum, data: &Vec<T>) -> Buffer {
+        let mut buffer_id: GLuint = 0;
 
         unsafe {
-            // send vertex data
-            gl::GenBuffers(1, &mut vertex_buffer_id as *mut u32);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer_id);
-            gl::BufferData(gl::ARRAY_BUFFER,
-                           (vertices.len() * mem::size_of::<GLfloat>()) as i64,
-                           mem::transmute(&vertices.as_slice()[0]),
-                           gl::STATIC_DRAW);
-
-            // send vertex index data
-            gl::GenBuffers(1, &mut element_buffer_id as *mut u32);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, element_buffer_id);
-            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER,
-                           (indices.len() * mem::size_of::<GLuint>()) as i64,
-                           mem::transmute(&indices.as_slice()[0]),
-                           gl::STATIC_DRAW);
-
-            // send vertex normal data
-            gl::GenBuffers(1, &mut normal_buffer_id as *mut u32);
-            gl::BindBuffer(gl::ARRAY_BUFFER, normal_buffer_id);
-            gl::BufferData(gl::ARRAY_BUFFER,
-                           (normals.len() * mem::size_of::<GLfloat>()) as i64,
-                           mem::transmute(&normals.as_slice()[0]),
-                           gl::STATIC_DRAW);
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::GenBuffers(1, &mut buffer_id as *mut u32);
         }
 
-        return Asset{
-            vertex_buffer_id: vertex_buffer_id,
-            element_buffer_id: element_buffer_id,
-            normal_buffer_id: normal_buffer_id,
-            num_vertices: vertices.len(),
-            num_indices: indices.len(),
-            num_normals: normals.len(),
-        };
+        let buffer = Buffer{ id: buffer_id, target: target, length: Cell::new(0) };
+        buffer.update(data);
+
+        return buffer;
+    }
+
+
+    /// Replaces the contents of the buffer, keeping the same buffer object so
+    /// that anything referring to it sees the new data.
+    pub fn update<T>(&self, data: &Vec<T>) {
+        unsafe {
+            let data_ptr = if data.is_empty() { ptr::null() } else { mem::transmute(&data.as_slice()[0]) };
+
+            verify!(gl::BindBuffer(self.target, self.id));
+            verify!(gl::BufferData(self.target,
+                                   (data.len() * mem::size_of::<T>()) as i64,
+                                   data_ptr,
+                                   gl::STATIC_DRAW));
+            verify!(gl::BindBuffer(self.target, 0));
+        }
+
+        self.length.set(data.len());
+    }
+
+
+    #[inline]
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+
+    /// The number of elements in the buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.length.get()
     }
 }
 
-#[unsafe_destructor]
-impl<'a> Drop for Asset<'a> {
+impl Drop for Buffer {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteBuffers(1, &self.vertex_buffer_id);
-            gl::DeleteBuffers(1, &self.element_buffer_id);
-            gl::DeleteBuffers(1, &self.normal_buffer_id);
+            gl::DeleteBuffers(1, &self.id);
         }
     }
 }
+
+
+/// A range of an asset's element buffer drawn with its own material.
+pub struct AssetPart {
+    name: Option<String>,
+    offset: usize,
+    count: usize,
+    origin: [f32; 3],
+    material: Option<graphics::Material>,
+    texture: Option<Rc<graphics::Texture>>,
+}
+
+impl AssetPart {
+    pub fn new(part: graphics::MeshPart, texture: Option<Rc<graphics::Texture>>) -> AssetPart {
+        AssetPart{
+            name: part.name,
+            offset: part.offset,
+            count: part.count,
+            origin: part.origin,
+            material: part.material,
+            texture: texture,
+        }
+    }
+
+
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|name| name.as_slice())
+    }
+
+
+    /// The index of the first element of the part.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+
+    /// The number of elements in the part.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+
+    /// The centre of the part's object or group, in the coordinates of the
+    /// asset.
+    #[inline]
+    pub fn origin(&self) -> [f32; 3] {
+        self.origin
+    }
+
+
+    #[inline]
+    pub fn material(&self) -> Option<&graphics::Material> {
+        self.material.as_ref()
+    }
+
+
+    /// The texture loaded for the material's diffuse map, if any.
+    #[inline]
+    pub fn texture(&self) -> Option<&graphics::Texture> {
+        self.texture.as_ref().map(|texture| &**texture)
+    }
+}
+
+
+/// A mesh uploaded to the GPU, all of its buffers are freed when it is
+/// dropped. Assets are created through the `GraphicsEngine`.
+pub struct Asset<'a> {
+    vertex_buffer: Buffer,
+    normal_buffer: Buffer,
+    uv_buffer: Buffer,
+    element_buffer: Buffer,
+    parts: RefCell<Vec<AssetPart>>,
+}
+
+impl<'a> Asset<'a> {
+    pub fn new(mesh: &graphics::Mesh, parts: Vec<AssetPart>) -> Asset<'a> {
+        Asset{
+            vertex_buffer: Buffer::new(gl::ARRAY_BUFFER, &mesh.vertices),
+            normal_buffer: Buffer::new(gl::ARRAY_BUFFER, &mesh.normals),
+            uv_buffer: Buffer::new(gl::ARRAY_BUFFER, &mesh.uvs),
+            element_buffer: Buffer::new(gl::ELEMENT_ARRAY_BUFFER, &mesh.indices),
+            parts: RefCell::new(parts),
+        }
+    }
+
+
+    /// Replaces the mesh in place, every object sharing the asset draws the
+    /// new mesh from then on.
+    pub fn reload(&self, mesh: &graphics::Mesh, parts: Vec<AssetPart>) {
+        self.vertex_buffer.update(&mesh.vertices);
+        self.normal_buffer.update(&mesh.normals);
+        self.uv_buffer.update(&mesh.uvs);
+        self.element_buffer.update(&mesh.indices);
+        *self.parts.borrow_mut() = parts;
+    }
+
+
+    #[inline]
+    pub fn vertex_buffer(&self) -> &Buffer {
+        &self.vertex_buffer
+    }
+
+
+    #[inline]
+    pub fn normal_buffer(&self) -> &Buffer {
+        &self.normal_buffer
+    }
+
+
+    /// The texture coordinate buffer, `None` if the mesh has no texture
+    /// coordinates.
+    #[inline]
+    pub fn uv_buffer(&self) -> Option<&Buffer> {
+        if self.uv_buffer.len() == 0 {
+            None
+        } else {
+            Some(&self.uv_buffer)
+        }
+    }
+
+
+    #[inline]
+    pub fn element_buffer(&self) -> &Buffer {
+        &self.element_buffer
+    }
+
+
+    #[inline]
+    pub fn parts(&self) -> Ref<Vec<AssetPart>> {
+        self.parts.borrow()
+    }
+
+
+    /// The distinct names of the objects and groups making up the asset, in
+    /// the order they first appear.
+    pub fn part_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+
+        for part in self.parts.borrow().iter() {
+            match part.name() {
+                Some(name) if !names.iter().any(|existing| existing.as_slice() == name) => names.push(name.to_string()),
+                _ => { /* do nothing */ }
+            }
+        }
+
+        return names;
+    }
+
+
+    pub fn has_part(&self, name: &str) -> bool {
+        self.parts.borrow().iter().any(|part| part.name() == Some(name))
+    }
+
+
+    /// The origin of the named object or group, `None` if the asset has no
+    /// such part.
+    pub fn part_origin(&self, name: &str) -> Option<[f32; 3]> {
+        self.parts.borrow().iter()
+            .find(|part| part.name() == Some(name))
+            .map(|part| part.origin())
+    }
+}
@@ -0,0 +1,179 @@
+use graphics;
+use graphics::file_watcher::FileWatcher;
+use std::collections::HashMap;
+use std::rc::{ self, Rc, Weak };
+
+/// Keeps track of the assets loaded from each file so that a file is only
+/// imported and uploaded once for each set of import options. The manager does
+/// not keep assets alive, an asset is freed as soon as the last object using it
+/// is dropped.
+pub struct AssetManager<'a> {
+    assets: HashMap<String, Vec<Entry<'a>>>,
+    dependencies: HashMap<String, Vec<String>>,
+    watcher: FileWatcher,
+}
+
+/// An asset loaded from a file, along with the options it was imported with.
+/// `ImportOptions` can not be hashed, so the variants of a file are searched.
+struct Entry<'a> {
+    options: graphics::ImportOptions,
+    asset: Weak<graphics::Asset<'a>>,
+}
+
+impl<'a> AssetManager<'a> {
+    pub fn new() -> AssetManager<'a> {
+        AssetManager{
+            assets: HashMap::new(),
+            dependencies: HashMap::new(),
+            watcher: FileWatcher::new(),
+        }
+    }
+
+
+    /// Returns the asset loaded from the path with the options, if it is still
+    /// in use.
+    pub fn get(&self, filepath: &str, options: &graphics::ImportOptions) -> Option<Rc<graphics::Asset<'a>>> {
+        self.assets.get(&normalize(filepath))
+            .and_then(|entries| entries.iter().find(|entry| entry.options == *options))
+            .and_then(|entry| entry.asset.upgrade())
+    }
+
+
+    /// Records the asset loaded from the path with the options and starts
+    /// watching the file, along with the other files the asset was built from.
+    pub fn insert(&mut self, filepath: &str, options: &graphics::ImportOptions, asset: &Rc<graphics::Asset<'a>>, dependencies: Vec<String>) {
+        let key = normalize(filepath);
+
+        if !self.assets.contains_key(&key) {
+            self.watcher.watch(key.as_slice());
+            self.assets.insert(key.clone(), Vec::new());
+        }
+
+        {
+            let entries = self.assets.get_mut(&key).unwrap();
+            entries.retain(|entry| entry.options != *options);
+            entries.push(Entry{ options: *options, asset: asset.downgrade() });
+        }
+
+        self.set_dependencies(filepath, dependencies);
+    }
+
+
+    /// Replaces the other files the asset loaded from the path was built from,
+    /// such as its material libraries and textures, and watches them for
+    /// changes.
+    pub fn set_dependencies(&mut self, filepath: &str, dependencies: Vec<String>) {
+        let key = normalize(filepath);
+        let dependencies: Vec<String> = dependencies.iter().map(|dependency| normalize(dependency.as_slice())).collect();
+
+        for dependency in dependencies.iter() {
+            if !self.is_watched(dependency.as_slice()) {
+                self.watcher.watch(dependency.as_slice());
+            }
+        }
+
+        let previous = self.dependencies.insert(key, dependencies).unwrap_or(Vec::new());
+        self.unwatch_unused(previous);
+    }
+
+
+    /// The number of references to the assets loaded from the path with any
+    /// options, 0 if they have all been freed or were never loaded.
+    pub fn reference_count(&self, filepath: &str) -> usize {
+        match self.assets.get(&normalize(filepath)) {
+            // the upgraded references do not count
+            Some(entries) => entries.iter()
+                .filter_map(|entry| entry.asset.upgrade())
+                .map(|asset| rc::strong_count(&asset) - 1)
+                .fold(0, |total, count| total + count),
+
+            None => 0,
+        }
+    }
+
+
+    /// Returns the assets still in use whose files have changed on disk since
+    /// they were loaded or last checked, along with their paths and the options
+    /// they were imported with. `time` is the current time in seconds.
+    pub fn changed_assets(&mut self, time: f64) -> Vec<(String, graphics::ImportOptions, Rc<graphics::Asset<'a>>)> {
+        let mut changed = Vec::new();
+
+        let changed_files = self.watcher.changed_files(time);
+
+        if changed_files.is_empty() {
+            return changed;
+        }
+
+        for (filepath, entries) in self.assets.iter() {
+            let dependencies = self.dependencies.get(filepath);
+            let has_changed = changed_files.iter().any(|changed_file| {
+                changed_file == filepath || dependencies.map(|files| files.contains(changed_file)).unwrap_or(false)
+            });
+
+            if !has_changed {
+                continue;
+            }
+
+            for entry in entries.iter() {
+                match entry.asset.upgrade() {
+                    Some(asset) => changed.push((filepath.clone(), entry.options, asset)),
+                    None => { /* do nothing */ }
+                }
+            }
+        }
+
+        return changed;
+    }
+
+
+    /// Forgets all assets which have been freed.
+    pub fn purge(&mut self) {
+        for entries in self.assets.values_mut() {
+            entries.retain(|entry| entry.asset.upgrade().is_some());
+        }
+
+        let freed: Vec<String> = self.assets.iter()
+            .filter(|&(_, entries)| entries.is_empty())
+            .map(|(filepath, _)| filepath.clone())
+            .collect();
+
+        for filepath in freed.iter() {
+            self.assets.remove(filepath);
+
+            let mut unused = self.dependencies.remove(filepath).unwrap_or(Vec::new());
+            unused.push(filepath.clone());
+            self.unwatch_unused(unused);
+        }
+    }
+
+
+    /// Whether the file is loaded as an asset or any asset was built from it.
+    fn is_watched(&self, filepath: &str) -> bool {
+        self.assets.contains_key(filepath) ||
+            self.dependencies.values().any(|dependencies| dependencies.iter().any(|dependency| dependency.as_slice() == filepath))
+    }
+
+
+    /// Stops watching the files which no asset needs any more.
+    fn unwatch_unused(&mut self, filepaths: Vec<String>) {
+        for filepath in filepaths.iter() {
+            if !self.is_watched(filepath.as_slice()) {
+                self.watcher.unwatch(filepath.as_slice());
+            }
+        }
+    }
+
+
+    /// The number of assets currently in use.
+    pub fn len(&self) -> usize {
+        self.assets.values()
+            .map(|entries| entries.iter().filter(|entry| entry.asset.upgrade().is_some()).count())
+            .fold(0, |total, count| total + count)
+    }
+}
+
+
+/// Different spellings of the same path share a single key.
+fn normalize(filepath: &str) -> String {
+    Path::new(filepath).as_str().unwrap_or(filepath).to_string()
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::io::fs;
+
+/// The number of seconds between checks of the files on disk, often enough
+/// to feel immediate without stat'ing every file on every frame.
+const POLL_INTERVAL: f64 = 0.25;
+
+/// Detects changes to files on disk by polling their modification times.
+pub struct FileWatcher {
+    modified_times: HashMap<String, u64>,
+    next_poll_time: f64,
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher{
+            modified_times: HashMap::new(),
+            next_poll_time: 0.0,
+        }
+    }
+
+
+    /// Starts watching the file, changes made before this call are not
+    /// reported.
+    pub fn watch(&mut self, filepath: &str) {
+        self.modified_times.insert(filepath.to_string(), modified_time(filepath));
+    }
+
+
+    pub fn unwatch(&mut self, filepath: &str) {
+        self.modified_times.remove(filepath);
+    }
+
+
+    /// Returns the files modified since they were last checked, `time` being
+    /// the current time in seconds. The files are only checked a few times per
+    /// second, calls in between return nothing. Files which cannot be read, for
+    /// instance while an editor is replacing them, are reported once they
+    /// reappear.
+    pub fn changed_files(&mut self, time: f64) -> Vec<String> {
+        let mut changed = Vec::new();
+
+        if time < self.next_poll_time {
+            return changed;
+        }
+
+        self.next_poll_time = time + POLL_INTERVAL;
+
+        for (filepath, previous_time) in self.modified_times.iter_mut() {
+            let time = modified_time(filepath.as_slice());
+
+            if time != 0 && time != *previous_time {
+                *previous_time = time;
+                changed.push(filepath.clone());
+            }
+        }
+
+        return changed;
+    }
+}
+
+
+/// The modification time of the file, 0 if it cannot be read.
+fn modified_time(filepath: &str) -> u64 {
+    match fs::stat(&Path::new(filepath)) {
+        Ok(stat) => stat.modified,
+        Err(_) => 0,
+    }
+}
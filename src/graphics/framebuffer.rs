@@ -0,0 +1,149 @@
+extern crate gl;
+extern crate image;
+
+use std::io::IoResult;
+use std::iter;
+use std::mem;
+use gl::types::*;
+
+/// An offscreen render target with colour and depth attachments, used to
+/// render without a visible window. Freed when it is dropped.
+pub struct Framebuffer {
+    id: GLuint,
+    color_renderbuffer_id: GLuint,
+    depth_renderbuffer_id: GLuint,
+    width: u32,
+    height: u32,
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer of the given size, returns an error describing
+    /// the problem if the driver cannot render to it.
+    pub fn new(width: u32, height: u32) -> Result<Framebuffer, String> {
+        let mut framebuffer = Framebuffer{
+            id: 0,
+            color_renderbuffer_id: 0,
+            depth_renderbuffer_id: 0,
+            width: width,
+            height: height,
+        };
+
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer.id);
+            verify!(gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id));
+
+            gl::GenRenderbuffers(1, &mut framebuffer.color_renderbuffer_id);
+            verify!(gl::BindRenderbuffer(gl::RENDERBUFFER, framebuffer.color_renderbuffer_id));
+            verify!(gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, width as GLsizei, height as GLsizei));
+            verify!(gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, framebuffer.color_renderbuffer_id));
+
+            gl::GenRenderbuffers(1, &mut framebuffer.depth_renderbuffer_id);
+            verify!(gl::BindRenderbuffer(gl::RENDERBUFFER, framebuffer.depth_renderbuffer_id));
+            verify!(gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width as GLsizei, height as GLsizei));
+            verify!(gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, framebuffer.depth_renderbuffer_id));
+
+            verify!(gl::BindRenderbuffer(gl::RENDERBUFFER, 0));
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            verify!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0));
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("the framebuffer is incomplete (status 0x{:04X})", status));
+            }
+        }
+
+        return Ok(framebuffer);
+    }
+
+
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+
+    /// Directs all rendering to the framebuffer.
+    pub fn bind(&self) {
+        unsafe {
+            verify!(gl::BindFramebuffer(gl::FRAMEBUFFER, self.id));
+            verify!(gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei));
+        }
+    }
+
+
+    /// Directs rendering back to the window.
+    pub fn unbind(&self) {
+        unsafe {
+            verify!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0));
+        }
+    }
+
+
+    /// Reads back the colour attachment.
+    pub fn read_pixels(&self) -> Screenshot {
+        self.bind();
+        let screenshot = Screenshot::read_pixels(self.width, self.height);
+        self.unbind();
+
+        return screenshot;
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.depth_renderbuffer_id);
+            gl::DeleteRenderbuffers(1, &self.color_renderbuffer_id);
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+
+/// An opaque RGBA image read back from the GPU, stored from the top row down.
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Reads the bottom-left `width` x `height` pixels of the framebuffer
+    /// currently bound, which may be the window's.
+    pub fn read_pixels(width: u32, height: u32) -> Screenshot {
+        let row_length = 4 * width as usize;
+        let mut rows: Vec<u8> = iter::repeat(0u8).take(row_length * height as usize).collect();
+
+        unsafe {
+            verify!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1));
+            verify!(gl::ReadPixels(0, 0, width as GLsizei, height as GLsizei, gl::RGBA, gl::UNSIGNED_BYTE, mem::transmute(rows.as_mut_ptr())));
+        }
+
+        // OpenGL returns the bottom row first
+        let mut pixels = Vec::with_capacity(rows.len());
+        for row in rows.as_slice().chunks(row_length).rev() {
+            pixels.push_all(row);
+        }
+
+        // the window is always shown opaque, whatever was left in the alpha
+        // channel by clearing and blending
+        for i in range(0us, pixels.len() / 4) {
+            pixels[4*i + 3] = 255;
+        }
+
+        return Screenshot{ width: width, height: height, pixels: pixels };
+    }
+
+
+    /// The RGBA value of the pixel `x` from the left and `y` from the top.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = 4 * (y * self.width + x) as usize;
+
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+
+    pub fn save_png(&self, filepath: &str) -> IoResult<()> {
+        image::save_buffer(&Path::new(filepath), self.pixels.as_slice(), self.width, self.height, image::ColorType::RGBA(8))
+    }
+}
@@ -0,0 +1,220 @@
+extern crate gl;
+
+use std::ffi;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{ AtomicBool, Ordering, ATOMIC_BOOL_INIT };
+use gl::types::*;
+
+/// What to do when an OpenGL error is detected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorMode {
+    /// Panics with the error and where it happened, the default.
+    Panic,
+    /// Prints the error and carries on, for drivers reporting spurious errors.
+    Log,
+}
+
+static LOG_ERRORS: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// The number of errors drained in a single check. Without a current context
+/// `glGetError` may never return `NO_ERROR`.
+const MAX_QUEUED_ERRORS: usize = 16;
+
+pub fn set_error_mode(mode: ErrorMode) {
+    LOG_ERRORS.store(mode == ErrorMode::Log, Ordering::SeqCst);
+}
+
+
+pub fn error_mode() -> ErrorMode {
+    if LOG_ERRORS.load(Ordering::SeqCst) { ErrorMode::Log } else { ErrorMode::Panic }
+}
+
+
+/// The name of an error code returned by `glGetError`.
+pub fn error_name(error: GLenum) -> &'static str {
+    match error {
+        gl::NO_ERROR => "GL_NO_ERROR",
+        gl::INVALID_ENUM => "GL_INVALID_ENUM",
+        gl::INVALID_VALUE => "GL_INVALID_VALUE",
+        gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+        gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+        gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+        _ => "unknown GL error",
+    }
+}
+
+
+/// A short explanation of an error code returned by `glGetError`.
+pub fn error_description(error: GLenum) -> &'static str {
+    match error {
+        gl::NO_ERROR => "no error",
+        gl::INVALID_ENUM => "an enum argument is out of range",
+        gl::INVALID_VALUE => "a numeric argument is out of range",
+        gl::INVALID_OPERATION => "the operation is not allowed in the current state",
+        gl::INVALID_FRAMEBUFFER_OPERATION => "the framebuffer object is not complete",
+        gl::OUT_OF_MEMORY => "there is not enough memory left to execute the command",
+        gl::STACK_UNDERFLOW => "an internal stack would underflow",
+        gl::STACK_OVERFLOW => "an internal stack would overflow",
+        _ => "the driver returned an undocumented error code",
+    }
+}
+
+
+/// Drains the OpenGL error queue, reporting every error along with `context`,
+/// typically the file, line and call which raised it. Panics or logs
+/// depending on the error mode, returns `true` if there were no errors.
+pub fn check_errors(context: &str) -> bool {
+    let errors = drain_errors();
+
+    return errors.is_empty() || report_errors(context, errors);
+}
+
+
+/// Drains the errors raised by unchecked calls made before the call at
+/// `location`, reporting them as coming from an earlier call rather than the
+/// one at `location`. Returns `true` if there were no errors.
+pub fn check_previous_errors(location: &str) -> bool {
+    let errors = drain_errors();
+
+    return errors.is_empty() || report_errors(format!("a previous call (before {})", location).as_slice(), errors);
+}
+
+
+fn drain_errors() -> Vec<GLenum> {
+    let mut errors = Vec::new();
+
+    for _ in range(0, MAX_QUEUED_ERRORS) {
+        let error = unsafe { gl::GetError() };
+
+        if error == gl::NO_ERROR {
+            break;
+        }
+
+        errors.push(error);
+    }
+
+    return errors;
+}
+
+
+/// Panics or logs depending on the error mode, always returns `false`.
+fn report_errors(context: &str, errors: Vec<GLenum>) -> bool {
+    let messages: Vec<String> = errors.iter()
+        .map(|&error| format!("{}: {} (0x{:04X}): {}", context, error_name(error), error, error_description(error)))
+        .collect();
+
+    match error_mode() {
+        ErrorMode::Panic => panic!("OpenGL error\n{}", messages.connect("\n")),
+
+        ErrorMode::Log => {
+            for message in messages.iter() {
+                println!("OpenGL error at {}", message);
+            }
+        }
+    }
+
+    return false;
+}
+
+
+/// Forwards driver messages from the `KHR_debug` extension to stdout, which
+/// explain errors in far more detail than `glGetError`. Notifications are left
+/// out. Returns `false` if the extension is not available.
+pub fn enable_debug_output() -> bool {
+    if !has_debug_output() {
+        return false;
+    }
+
+    unsafe {
+        verify!(gl::Enable(gl::DEBUG_OUTPUT));
+        // report messages from within the offending call
+        verify!(gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS));
+        verify!(gl::DebugMessageCallback(debug_message_callback, ptr::null()));
+        // some drivers describe every buffer operation in a notification,
+        // which would flood the output on every frame
+        verify!(gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DEBUG_SEVERITY_NOTIFICATION, 0, ptr::null(), gl::FALSE));
+    }
+
+    return true;
+}
+
+
+/// Whether the context supports `KHR_debug`, which is core since OpenGL 4.3.
+/// The entry points are not enough to go by, as on GLX they resolve whether
+/// or not the driver implements them.
+fn has_debug_output() -> bool {
+    let (mut major, mut minor) = (0, 0);
+    let mut num_extensions = 0;
+
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut num_extensions);
+    }
+
+    if (major, minor) >= (4, 3) {
+        return true;
+    }
+
+    for i in range(0, num_extensions) {
+        let name = unsafe { gl::GetStringi(gl::EXTENSIONS, i as GLuint) } as *const GLchar;
+
+        if !name.is_null() && unsafe { ffi::c_str_to_bytes(&name) } == b"GL_KHR_debug" {
+            return true;
+        }
+    }
+
+    return false;
+}
+
+
+/// Always logs, even in `ErrorMode::Panic`, as unwinding into the driver is
+/// not allowed. The errors are still raised by the next `check_errors`.
+extern "system" fn debug_message_callback(source: GLenum, message_type: GLenum, id: GLuint, severity: GLenum, length: GLsizei, message: *const GLchar, _: *mut GLvoid) {
+    let bytes = unsafe { slice::from_raw_buf(&(message as *const u8), length as usize) };
+
+    println!("OpenGL {} {} {} [{}]: {}",
+             debug_severity_name(severity),
+             debug_source_name(source),
+             debug_type_name(message_type),
+             id,
+             String::from_utf8_lossy(bytes));
+}
+
+
+fn debug_source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+
+fn debug_type_name(message_type: GLenum) -> &'static str {
+    match message_type {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behaviour",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behaviour",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        _ => "message",
+    }
+}
+
+
+fn debug_severity_name(severity: GLenum) -> &'static str {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => "high severity",
+        gl::DEBUG_SEVERITY_MEDIUM => "medium severity",
+        gl::DEBUG_SEVERITY_LOW => "low severity",
+        _ => "notification",
+    }
+}
@@ -1,192 +1,297 @@
 extern crate gl;
 extern crate glfw;
+extern crate image;
 extern crate mithril;
 
 use std::mem;
 use std::ptr;
-use std::str;
-use std::iter;
+use std::io;
 use std::rc::Rc;
+use std::cmp::Ordering;
 use gl::types::*;
 use self::mithril::math::Vector;
-use std::ffi::CString;
+use std::default::Default;
 use graphics;
+use graphics::{ Asset, AssetPart };
+use graphics::file_watcher::FileWatcher;
 
-macro_rules! verify(
-    ($e: expr) => {
-        {
-            let result = $e;
-            assert_eq!(gl::GetError(), 0);
-            result
-        }
-    }
-);
+const VERTEX_SHADER_PATH: &'static str = "assets/shaders/default.vert";
+const FRAGMENT_SHADER_PATH: &'static str = "assets/shaders/default.frag";
+
+/// The names of the vertex attributes streamed from every asset. Programs
+/// used by the engine may declare any of them.
+const POSITION_ATTRIBUTE: &'static str = "vertex_pos";
+const NORMAL_ATTRIBUTE: &'static str = "vertex_norm";
+const UV_ATTRIBUTE: &'static str = "vertex_uv";
 
 pub struct GraphicsEngine<'a> {
     camera: graphics::Camera,
-    program_id: GLuint,
-    vertex_shader_id: GLuint,
-    fragment_shader_id: GLuint,
-    color_id: GLint,
-    model_matrix_id: GLint,
-    view_matrix_id: GLint,
-    projection_matrix_id: GLint,
-    objects: Vec<graphics::Object<'a>>,
-    assets: Vec<Rc<Asset<'a>>>,
+    program: graphics::ShaderProgram,
+    objects: graphics::ObjectPool<graphics::Object<'a>>,
+    assets: graphics::AssetManager<'a>,
+    shader_watcher: FileWatcher,
     assets_vertex_array_id: GLuint,
 }
 
-pub struct Buffer {
-    id: GLuint,
-    length: usize,
-}
-
-pub struct Asset<'a> {
-    vertex_buffer: Buffer,
-    normal_buffer: Buffer,
-    element_buffer: Buffer,
-}
-
 impl<'a> GraphicsEngine<'a> {
     pub fn new(window: &glfw::Window) -> GraphicsEngine<'a> {
+        gl::load_with(|s| window.get_proc_address(s));
+
+        let program = match new_default_program() {
+            Ok(program) => program,
+            Err(log) => panic!("Failed to build the shader program: {}", log),
+        };
+
         let mut graphics = GraphicsEngine{
             camera: graphics::Camera::new(Vector::new(4.0, 4.0, 4.0), Vector::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
-            program_id: 0,
-            vertex_shader_id: 0,
-            fragment_shader_id: 0,
-            color_id: -1,
-            model_matrix_id: -1,
-            view_matrix_id: -1,
-            projection_matrix_id: -1,
-            assets: Vec::new(),
+            program: program,
+            assets: graphics::AssetManager::new(),
+            shader_watcher: FileWatcher::new(),
             assets_vertex_array_id: 0,
-            objects: Vec::new(),
+            objects: graphics::ObjectPool::new(),
         };
 
-        gl::load_with(|s| window.get_proc_address(s));
         graphics.initialize();
 
         return graphics;
     }
 
     fn initialize(&mut self) {
-        self.vertex_shader_id = compile_shader(gl::VERTEX_SHADER, "
-        #version 150
+        if graphics::gl_error::enable_debug_output() {
+            println!("OpenGL debug output enabled");
+        }
 
-        uniform mat4 model_matrix;
-        uniform mat4 view_matrix;
-        uniform mat4 projection_matrix;
+        self.shader_watcher.watch(VERTEX_SHADER_PATH);
+        self.shader_watcher.watch(FRAGMENT_SHADER_PATH);
 
-        in vec3 vertex_pos;
-        in vec3 vertex_norm;
+        unsafe {
+            gl::GenVertexArrays(1, &mut self.assets_vertex_array_id as *mut u32);
 
-        out vec3 normal;
+            verify!(self.program.use_program());
 
-        void main(void) {
-            gl_Position = projection_matrix * view_matrix * model_matrix * vec4(vertex_pos, 1.0);
-            normal = vec3(view_matrix * model_matrix * vec4(vertex_norm, 0.0));
+            verify!(gl::ClearColor(0.1, 0.4, 0.2, 1.0));
+            verify!(gl::LineWidth(1.0));
+            verify!(gl::Enable(gl::DEPTH_TEST));
+            verify!(gl::DepthFunc(gl::LESS));
+            verify!(gl::ClearDepth(1.0));
+            verify!(gl::Enable(gl::BLEND));
+            verify!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
         }
-        ");
 
-        self.fragment_shader_id = compile_shader(gl::FRAGMENT_SHADER, "
-        #version 150
+        self.program.set_int("diffuse_texture", 0);
+    }
+
 
-        uniform vec4 color;
+    /// Rebuilds the shader program if any of the shader files have changed on
+    /// disk, `time` being the current time in seconds. The previous program is
+    /// kept if the new one fails to build.
+    pub fn reload_changed_shaders(&mut self, time: f64) {
+        if self.shader_watcher.changed_files(time).is_empty() {
+            return;
+        }
+
+        match new_default_program() {
+            Ok(program) => {
+                self.program = program;
+                self.program.use_program();
+                self.program.set_int("diffuse_texture", 0);
+
+                println!("Reloaded shaders");
+            }
 
-        in vec3 normal;
+            Err(log) => println!("Failed to reload shaders, keeping the previous program:\n{}", log),
+        }
+    }
 
-        out vec4 out_color;
 
-        void main(void) {
-            const vec3 vertex_to_light = normalize(vec3(1.0, 1.0, 0.0));
+    /// Loads an asset from an OBJ file with the default import options,
+    /// returning the same asset for as long as it is still in use by any
+    /// object.
+    pub fn new_asset_from_file(&mut self, filepath: &str) -> Result<Rc<Asset<'a>>, graphics::ImportError> {
+        self.new_asset_from_file_with_options(filepath, &Default::default())
+    }
 
-            float diffuse = clamp(pow(dot(normal, vertex_to_light), 3), 0.0, 0.7) + 0.3;
 
-            out_color = vec4(color.xyz * diffuse, 1.0);
+    /// Loads an asset from an OBJ file, for instance with flat normals. The
+    /// same asset is returned for the same file and options for as long as it
+    /// is still in use, and it is re-imported with the same options when the
+    /// file changes.
+    pub fn new_asset_from_file_with_options(&mut self, filepath: &str, options: &graphics::ImportOptions) -> Result<Rc<Asset<'a>>, graphics::ImportError> {
+        match self.assets.get(filepath, options) {
+            Some(asset_ref) => return Ok(asset_ref),
+            None => { /* do nothing */ }
         }
-        ");
 
-        self.program_id = link_program(self.vertex_shader_id, self.fragment_shader_id);
+        let mesh = try!(graphics::utils::import_from_obj(filepath, options));
+        let dependencies = mesh.dependencies();
+        let asset_ref = self.new_asset_from_mesh(mesh, filepath);
 
+        self.assets.purge();
+        self.assets.insert(filepath, options, &asset_ref, dependencies);
 
-        unsafe {
-            gl::GenVertexArrays(1, &mut self.assets_vertex_array_id as *mut u32);
+        return Ok(asset_ref);
+    }
 
-            verify!(gl::UseProgram(self.program_id));
 
-            gl::ClearColor(0.1, 0.4, 0.2, 0.9);
-            gl::LineWidth(1.0);
-            verify!(gl::Enable(gl::DEPTH_TEST));
-            gl::DepthFunc(gl::LESS);
-            gl::ClearDepth(1.0);
+    /// The number of references to the assets loaded from the file with any
+    /// import options, including one for every object using them.
+    pub fn asset_reference_count(&self, filepath: &str) -> usize {
+        self.assets.reference_count(filepath)
+    }
+
+
+    /// Imports an OBJ mesh from any buffered source with the default import
+    /// options, such as bytes embedded with `include_bytes!`. Material
+    /// libraries are not loaded, so every part uses the default material.
+    pub fn new_asset_from_reader<R: io::Buffer>(&mut self, reader: &mut R, name: &str) -> Result<Rc<Asset<'a>>, graphics::ImportError> {
+        self.new_asset_from_reader_with_options(reader, name, &Default::default())
+    }
 
-            let model_variable_name = CString::from_slice("model_matrix".as_bytes());
-            self.model_matrix_id = gl::GetUniformLocation(self.program_id, model_variable_name.as_ptr());
 
-            let color_variable_name = CString::from_slice("color".as_bytes());
-            self.color_id = gl::GetUniformLocation(self.program_id, color_variable_name.as_ptr());
-            gl::Uniform4fv(self.color_id, 1, mem::transmute(&[1.0f32, 0.0f32, 0.0f32, 1.0f32][0]));
+    /// Imports an OBJ mesh from any buffered source, for instance with flat
+    /// normals.
+    pub fn new_asset_from_reader_with_options<R: io::Buffer>(&mut self, reader: &mut R, name: &str, options: &graphics::ImportOptions) -> Result<Rc<Asset<'a>>, graphics::ImportError> {
+        let mesh = try!(graphics::utils::import_obj_from_reader(reader, name, None, options));
+
+        return Ok(self.new_asset_from_mesh(mesh, name));
+    }
 
-            let view_matrix_variable_name = CString::from_slice("view_matrix".as_bytes());
-            self.view_matrix_id = gl::GetUniformLocation(self.program_id, view_matrix_variable_name.as_ptr());
 
-            let projection_matrix_variable_name = CString::from_slice("projection_matrix".as_bytes());
-            self.projection_matrix_id = gl::GetUniformLocation(self.program_id, projection_matrix_variable_name.as_ptr());
+    /// Uploads the mesh to the GPU, `name` identifies the mesh in messages.
+    pub fn new_asset_from_mesh(&mut self, mut mesh: graphics::Mesh, name: &str) -> Rc<Asset<'a>> {
+        let mesh_parts = mem::replace(&mut mesh.parts, Vec::new());
+        let parts = self.new_asset_parts(mesh_parts, name);
+
+        return Rc::new(Asset::new(&mesh, parts));
+    }
+
+
+    /// Re-imports the assets whose files, material libraries or textures have
+    /// changed on disk, updating them in place for every object sharing them.
+    /// Each asset is imported with the options it was first loaded with, and
+    /// `time` is the current time in seconds. An asset which fails to import
+    /// keeps its previous mesh.
+    pub fn reload_changed_assets(&mut self, time: f64) {
+        for (filepath, options, asset) in self.assets.changed_assets(time).into_iter() {
+            match graphics::utils::import_from_obj(filepath.as_slice(), &options) {
+                Ok(mut mesh) => {
+                    self.assets.set_dependencies(filepath.as_slice(), mesh.dependencies());
+
+                    let mesh_parts = mem::replace(&mut mesh.parts, Vec::new());
+                    let parts = self.new_asset_parts(mesh_parts, filepath.as_slice());
+
+                    asset.reload(&mesh, parts);
+                    println!("Reloaded asset {:?}", filepath);
+                }
+
+                Err(err) => println!("Failed to reload asset: {}", err),
+            }
         }
     }
 
 
-    pub fn new_asset_from_file(&mut self, filepath: &str) -> Rc<Asset> {
-        let (vertices, normals, indices) = graphics::utils::import_from_obj(filepath);
-        let mut vertex_buffer_id: GLuint = 0;
-        let mut element_buffer_id: GLuint = 0;
-        let mut normal_buffer_id: GLuint = 0;
+    /// Loads the textures of the parts' materials.
+    fn new_asset_parts(&mut self, mesh_parts: Vec<graphics::MeshPart>, name: &str) -> Vec<AssetPart> {
+        let mut parts = Vec::new();
 
-        unsafe {
-            // send vertex data
-            gl::GenBuffers(1, &mut vertex_buffer_id as *mut u32);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer_id);
-            gl::BufferData(gl::ARRAY_BUFFER,
-                           (vertices.len() * mem::size_of::<GLfloat>()) as i64,
-                           mem::transmute(&vertices.as_slice()[0]),
-                           gl::STATIC_DRAW);
-
-            // send vertex index data
-            gl::GenBuffers(1, &mut element_buffer_id as *mut u32);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, element_buffer_id);
-            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER,
-                           (indices.len() * mem::size_of::<GLuint>()) as i64,
-                           mem::transmute(&indices.as_slice()[0]),
-                           gl::STATIC_DRAW);
-
-            // send vertex normal data
-            gl::GenBuffers(1, &mut normal_buffer_id as *mut u32);
-            gl::BindBuffer(gl::ARRAY_BUFFER, normal_buffer_id);
-            gl::BufferData(gl::ARRAY_BUFFER,
-                           (normals.len() * mem::size_of::<GLfloat>()) as i64,
-                           mem::transmute(&normals.as_slice()[0]),
-                           gl::STATIC_DRAW);
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        for part in mesh_parts.into_iter() {
+            let texture = match part.material {
+                Some(graphics::Material{ diffuse_map: Some(ref texture_path), .. }) => {
+                    match self.new_texture_from_file(texture_path.as_slice()) {
+                        Ok(texture_ref) => Some(texture_ref),
+
+                        Err(err) => {
+                            println!("Failed to load texture {:?} for {:?}: {:?}", texture_path, name, err);
+                            None
+                        }
+                    }
+                }
+
+                _ => None,
+            };
+
+            parts.push(AssetPart::new(part, texture));
         }
 
-        let asset_ref = Rc::new(Asset{
-            vertex_buffer: Buffer{ id: vertex_buffer_id, length: vertices.len() },
-            normal_buffer: Buffer{ id: normal_buffer_id, length: normals.len() },
-            element_buffer: Buffer{ id: element_buffer_id, length: indices.len() },
-        });
-        self.assets.push(asset_ref.clone());
+        return parts;
+    }
+
+
+    /// Loads a texture from an image file on disk, typically a PNG.
+    pub fn new_texture_from_file(&mut self, filepath: &str) -> image::ImageResult<Rc<graphics::Texture>> {
+        let texture = try!(graphics::Texture::new_from_file(filepath));
+
+        return Ok(Rc::new(texture));
+    }
+
 
-        return asset_ref;
+    /// Creates an object drawing the whole asset, the returned handle remains
+    /// valid until the object is removed.
+    pub fn create_object_from_asset(&mut self, asset: Rc<Asset<'a>>) -> graphics::ObjectHandle {
+        self.objects.insert(graphics::Object::new(asset))
     }
 
 
-    pub fn create_object_from_asset(&mut self, asset: Rc<Asset<'a>>) -> &mut graphics::Object<'a> {
-        self.objects.push(graphics::Object::new(asset.clone()));
+    /// Creates an object which only draws the named object or group of the
+    /// asset, returns `None` if the asset has no such part. The object is
+    /// positioned relative to the centre of the part, and starts out where the
+    /// part was authored.
+    pub fn create_object_from_asset_part(&mut self, asset: Rc<Asset<'a>>, name: &str) -> Option<graphics::ObjectHandle> {
+        let origin = match asset.part_origin(name) {
+            Some(origin) => origin,
+            None => return None,
+        };
+
+        let mut object = graphics::Object::new(asset);
+        object.set_part(Some(name.to_string()));
+        object.set_translation(origin[0], origin[1], origin[2]);
+
+        return Some(self.objects.insert(object));
+    }
+
 
-        // compiler HAX
-        let index = self.objects.len() - 1;
-        return &mut self.objects[index];
+    /// Looks up an object, returns `None` if it has been removed.
+    pub fn object(&self, handle: graphics::ObjectHandle) -> Option<&graphics::Object<'a>> {
+        self.objects.get(handle)
+    }
+
+
+    pub fn object_mut(&mut self, handle: graphics::ObjectHandle) -> Option<&mut graphics::Object<'a>> {
+        self.objects.get_mut(handle)
+    }
+
+
+    /// Removes the object from the scene, returning `false` if it had already
+    /// been removed. Its asset is freed once no other objects use it.
+    pub fn remove_object(&mut self, handle: graphics::ObjectHandle) -> bool {
+        self.objects.remove(handle).is_some()
+    }
+
+
+    /// Removes every object drawing the asset, freeing the asset once no other
+    /// references to it remain. Returns the number of objects removed.
+    pub fn remove_objects_using_asset(&mut self, asset: &Asset<'a>) -> usize {
+        let handles: Vec<graphics::ObjectHandle> = self.objects.iter()
+            .filter(|&(_, object)| object.asset() as *const Asset == asset as *const Asset)
+            .map(|(handle, _)| handle)
+            .collect();
+
+        for handle in handles.iter() {
+            self.objects.remove(*handle);
+        }
+
+        return handles.len();
+    }
+
+
+    /// Removes every object, leaving an empty scene.
+    pub fn remove_all_objects(&mut self) {
+        self.objects.clear();
+    }
+
+
+    pub fn objects_mut(&mut self) -> graphics::object_pool::IterMut<graphics::Object<'a>> {
+        self.objects.iter_mut()
     }
 
 
@@ -195,117 +300,181 @@ impl<'a> GraphicsEngine<'a> {
     }
 
 
-    pub fn draw(&self) {
+    /// Renders the scene, with `alpha` being the fraction of the way between
+    /// the previous and current state of each object.
+    pub fn draw(&self, alpha: f32) {
+        let view_matrix = self.camera.view_matrix();
+
         unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            verify!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
 
-            let view_matrix = self.camera.view_matrix();
-            gl::UniformMatrix4fv(self.view_matrix_id, 1, gl::TRUE, mem::transmute(&view_matrix[0]));
+            self.program.set_mat4("view_matrix", &view_matrix);
+            self.program.set_mat4("projection_matrix", &self.camera.projection_matrix());
 
-            let projection_matrix = self.camera.projection_matrix();
-            gl::UniformMatrix4fv(self.projection_matrix_id, 1, gl::TRUE, mem::transmute(&projection_matrix[0]));
+            verify!(gl::BindVertexArray(self.assets_vertex_array_id));
+        }
 
-            // draw simple objects
-            gl::BindVertexArray(self.assets_vertex_array_id);
-            for object in self.objects.iter() {
-                self.render_object(object);
+        // draw the opaque parts first, remembering the objects with any
+        // transparent parts along with their depth
+        let mut transparent_objects: Vec<(f32, &graphics::Object)> = Vec::new();
+        for (_, object) in self.objects.iter() {
+            if self.render_object(object, &view_matrix, alpha, false) {
+                let model_view_matrix = graphics::matrix::multiply(&view_matrix, &object.interpolated_model_matrix(alpha));
+                transparent_objects.push((model_view_matrix[11], object));
             }
-            gl::BindVertexArray(0);
         }
-    }
 
+        // transparent parts are blended over everything behind them, so they
+        // are drawn from back to front without hiding each other
+        transparent_objects.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
 
-    fn render_object(&self, object: &graphics::Object) {
-        let asset = object.asset();
+        unsafe {
+            verify!(gl::DepthMask(gl::FALSE));
+        }
+
+        for &(_, object) in transparent_objects.iter() {
+            self.render_object(object, &view_matrix, alpha, true);
+        }
 
         unsafe {
-            gl::UniformMatrix4fv(self.model_matrix_id, 1, gl::TRUE, mem::transmute(&object.model_matrix()[0]));
+            verify!(gl::DepthMask(gl::TRUE));
+            verify!(gl::BindVertexArray(0));
+        }
+    }
 
-            gl::EnableVertexAttribArray(0);
-            gl::EnableVertexAttribArray(1);
 
-            // set up the vertex data pointer
-            gl::BindBuffer(gl::ARRAY_BUFFER, asset.vertex_buffer.id);
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 0, ptr::null());
+    /// Draws either the opaque or the transparent parts of the object, returns
+    /// `true` if any parts of the other kind were skipped.
+    fn render_object(&self, object: &graphics::Object, view_matrix: &[f32; 16], alpha: f32, transparent: bool) -> bool {
+        let asset = object.asset();
+        let model_matrix = object.interpolated_model_matrix(alpha);
+        let normal_matrix = graphics::matrix::normal_matrix(&graphics::matrix::multiply(view_matrix, &model_matrix));
+        let mut skipped = false;
 
-            // set up the normal data pointer
-            gl::BindBuffer(gl::ARRAY_BUFFER, asset.normal_buffer.id);
-            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, 0, ptr::null());
+        unsafe {
+            self.program.set_mat4("model_matrix", &model_matrix);
+            self.program.set_mat3("normal_matrix", &normal_matrix);
+
+            // only stream the attributes the program declares, a wireframe
+            // shader for instance has no use for normals or uvs
+            let enabled_attributes: Vec<GLuint> = [
+                self.bind_attribute(POSITION_ATTRIBUTE, Some(asset.vertex_buffer()), 3),
+                self.bind_attribute(NORMAL_ATTRIBUTE, Some(asset.normal_buffer()), 3),
+                self.bind_attribute(UV_ATTRIBUTE, asset.uv_buffer(), 2),
+            ].iter().filter_map(|location| *location).collect();
 
             // unbind the buffers, no longer need to modify the pointers
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            verify!(gl::BindBuffer(gl::ARRAY_BUFFER, 0));
 
             // bind the common index array
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, asset.element_buffer.id);
+            verify!(gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, asset.element_buffer().id()));
             verify!(gl::Enable(gl::DEPTH_TEST));
-            gl::DrawElements(gl::TRIANGLES, asset.element_buffer.length as i32, gl::UNSIGNED_INT, ptr::null());
 
-            gl::DisableVertexAttribArray(1);
-            gl::DisableVertexAttribArray(0);
-        }
-    }
-}
+            let default_material: graphics::Material = Default::default();
+            // textures are modulated by the diffuse colour, so untinted by default
+            let default_textured_material = graphics::Material{ diffuse: [1.0; 3], .. Default::default() };
+
+            let parts = asset.parts();
+            for part in parts.iter() {
+                if object.part().is_some() && object.part() != part.name() {
+                    continue;
+                }
+
+                // a texture set on the object takes precedence over the asset's
+                let texture = match (asset.uv_buffer(), object.texture()) {
+                    (Some(_), Some(texture)) => Some(texture),
+                    (Some(_), None) => part.texture(),
+                    (None, _) => None,
+                };
+
+                // likewise for the object's material
+                let material = match (object.material(), part.material(), texture) {
+                    (Some(material), _, _) => material,
+                    (None, Some(material), _) => material,
+                    (None, None, Some(_)) => &default_textured_material,
+                    (None, None, None) => &default_material,
+                };
+
+                if (material.opacity < 1.0) != transparent {
+                    skipped = true;
+                    continue;
+                }
+
+                self.upload_material(material);
+
+                match texture {
+                    Some(texture) => {
+                        self.program.set_bool("use_texture", true);
+                        verify!(gl::ActiveTexture(gl::TEXTURE0));
+                        verify!(gl::BindTexture(gl::TEXTURE_2D, texture.id()));
+                    }
+
+                    None => {
+                        self.program.set_bool("use_texture", false);
+                    }
+                }
+
+                verify!(gl::DrawElements(gl::TRIANGLES, part.count() as i32, gl::UNSIGNED_INT, (part.offset() * mem::size_of::<GLuint>()) as *const GLvoid));
+            }
 
-#[unsafe_destructor]
-impl<'a> Drop for GraphicsEngine<'a> {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteProgram(self.program_id);
-            gl::DeleteShader(self.fragment_shader_id);
-            gl::DeleteShader(self.vertex_shader_id);
-            gl::DeleteVertexArrays(1, &self.assets_vertex_array_id);
+            verify!(gl::BindTexture(gl::TEXTURE_2D, 0));
 
-            for asset in self.assets.iter() {
-                gl::DeleteBuffers(1, &asset.vertex_buffer.id);
-                gl::DeleteBuffers(1, &asset.element_buffer.id);
-                gl::DeleteBuffers(1, &asset.normal_buffer.id);
+            for &location in enabled_attributes.iter() {
+                verify!(gl::DisableVertexAttribArray(location));
             }
         }
+
+        return skipped;
     }
-}
 
-fn compile_shader(shader_type: GLenum, shader_source: &str) -> GLuint {
-    let shader_source_c_str = CString::from_slice(shader_source.as_bytes());
 
-    unsafe {
-        let shader = gl::CreateShader(shader_type);
+    /// Streams the buffer into the named attribute of the program, returning
+    /// the location enabled. Attributes without data are set to zero instead,
+    /// and nothing happens if the program does not declare the attribute.
+    fn bind_attribute(&self, name: &str, buffer: Option<&graphics::Buffer>, size: GLint) -> Option<GLuint> {
+        let location = match self.program.attribute_location(name) {
+            Some(location) => location,
+            None => return None,
+        };
+
+        unsafe {
+            match buffer {
+                Some(buffer) => {
+                    verify!(gl::EnableVertexAttribArray(location));
+                    verify!(gl::BindBuffer(gl::ARRAY_BUFFER, buffer.id()));
+                    verify!(gl::VertexAttribPointer(location, size, gl::FLOAT, gl::FALSE, 0, ptr::null()));
 
-        gl::ShaderSource(shader, 1, &shader_source_c_str.as_ptr(), ptr::null());
-        gl::CompileShader(shader);
+                    return Some(location);
+                }
 
-        let mut status = gl::FALSE as GLint;
-        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+                None => {
+                    verify!(gl::VertexAttrib4f(location, 0.0, 0.0, 0.0, 1.0));
 
-        if status != (gl::TRUE as GLint) {
-            let mut len = 0;
-            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-            let mut buffer: Vec<u8> = iter::repeat(0u8).take(len as usize - 1).collect();
-            gl::GetShaderInfoLog(shader, len, ptr::null_mut(), buffer.as_mut_ptr() as *mut GLchar);
-            panic!("{}", str::from_utf8(buffer.as_slice()).unwrap());
+                    return None;
+                }
+            }
         }
+    }
 
-        return shader;
+
+    fn upload_material(&self, material: &graphics::Material) {
+        let color = [material.diffuse[0], material.diffuse[1], material.diffuse[2], material.opacity];
+
+        self.program.set_vec4("color", &color);
+        self.program.set_vec3("specular", &material.specular);
+        self.program.set_float("shininess", material.shininess);
     }
 }
 
-fn link_program(vertex_shader_id: GLuint, fragment_shader_id: GLuint) -> GLuint {
-    unsafe {
-        let program_id = gl::CreateProgram();
-        gl::AttachShader(program_id, vertex_shader_id);
-        gl::AttachShader(program_id, fragment_shader_id);
-        gl::LinkProgram(program_id);
-
-        let mut status = gl::FALSE as GLint;
-        gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut status);
-
-        if status != (gl::TRUE as GLint) {
-            let mut len = 0;
-            gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut len);
-            let mut buffer: Vec<u8> = iter::repeat(0u8).take(len as usize - 1).collect();
-            gl::GetProgramInfoLog(program_id, len, ptr::null_mut(), buffer.as_mut_ptr() as *mut GLchar);
-            panic!("{}", str::from_utf8(buffer.as_slice()).unwrap());
+#[unsafe_destructor]
+impl<'a> Drop for GraphicsEngine<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.assets_vertex_array_id);
         }
-
-        return program_id;
     }
 }
+
+fn new_default_program() -> Result<graphics::ShaderProgram, String> {
+    graphics::ShaderProgram::new_from_files(VERTEX_SHADER_PATH, FRAGMENT_SHADER_PATH, &[POSITION_ATTRIBUTE, NORMAL_ATTRIBUTE, UV_ATTRIBUTE])
+}
@@ -0,0 +1,29 @@
+use std::default::Default;
+
+/// Surface properties used to shade an object, mirroring the subset of the
+/// MTL format supported by the importer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    /// Base colour of the surface (`Kd`).
+    pub diffuse: [f32; 3],
+    /// Colour of specular highlights (`Ks`).
+    pub specular: [f32; 3],
+    /// Specular exponent, higher values give smaller highlights (`Ns`).
+    pub shininess: f32,
+    /// 1.0 is fully opaque and 0.0 is fully transparent (`d`).
+    pub opacity: f32,
+    /// Path to an image modulating the diffuse colour (`map_Kd`).
+    pub diffuse_map: Option<String>,
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material{
+            diffuse: [1.0, 0.0, 0.0],
+            specular: [0.0; 3],
+            shininess: 0.0,
+            opacity: 1.0,
+            diffuse_map: None,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::num::Float;
+
+/// Multiplies two row-major 4x4 matrices.
+pub fn multiply(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut result = [0.0f32; 16];
+
+    for row in range(0us, 4us) {
+        for col in range(0us, 4us) {
+            let mut sum = 0.0;
+            for k in range(0us, 4us) {
+                sum = sum + a[4*row + k] * b[4*k + col];
+            }
+            result[4*row + col] = sum;
+        }
+    }
+
+    return result;
+}
+
+
+/// Computes the row-major 3x3 matrix used to transform normals, the inverse
+/// transpose of the upper-left 3x3 block of the given row-major 4x4 matrix.
+/// Falls back to the upper-left block itself if it is singular.
+pub fn normal_matrix(m: &[f32; 16]) -> [f32; 9] {
+    let (a, b, c) = (m[0], m[1], m[2]);
+    let (d, e, f) = (m[4], m[5], m[6]);
+    let (g, h, i) = (m[8], m[9], m[10]);
+
+    // cofactors of the 3x3 block
+    let c00 = e*i - f*h;
+    let c01 = f*g - d*i;
+    let c02 = d*h - e*g;
+    let c10 = c*h - b*i;
+    let c11 = a*i - c*g;
+    let c12 = b*g - a*h;
+    let c20 = b*f - c*e;
+    let c21 = c*d - a*f;
+    let c22 = a*e - b*d;
+
+    let determinant = a*c00 + b*c01 + c*c02;
+
+    if determinant.abs() < 1e-12 {
+        return [a, b, c, d, e, f, g, h, i];
+    }
+
+    // the inverse is the transposed cofactor matrix over the determinant, so
+    // the inverse transpose is simply the cofactor matrix over the determinant
+    let inv = 1.0 / determinant;
+
+    [
+        c00*inv, c01*inv, c02*inv,
+        c10*inv, c11*inv, c12*inv,
+        c20*inv, c21*inv, c22*inv,
+    ]
+}
+
+
+#[test]
+fn normal_matrix_test() {
+    let m = [
+        2.0, 0.0, 0.0, 5.0,
+        0.0, 4.0, 0.0, 6.0,
+        0.0, 0.0, 0.5, 7.0,
+        0.0, 0.0, 0.0, 1.0,
+    ];
+
+    let n = normal_matrix(&m);
+
+    // non-uniform scale is inverted and translation is discarded
+    assert_eq!(n, [0.5, 0.0, 0.0, 0.0, 0.25, 0.0, 0.0, 0.0, 2.0]);
+
+    // a normal stays perpendicular to a transformed tangent on a sheared plane
+    let shear = [
+        1.0, 1.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ];
+    let n = normal_matrix(&shear);
+    let tangent = [shear[1], shear[5], shear[9]];
+    let normal = [n[0], n[3], n[6]];
+    let dot = tangent[0]*normal[0] + tangent[1]*normal[1] + tangent[2]*normal[2];
+    assert!(dot.abs() < 1e-6);
+}
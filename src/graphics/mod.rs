@@ -1,8 +1,44 @@
+/// Evaluates a GL call and checks for errors, reporting the call and where it
+/// was made according to the `gl_error` mode. Errors left over from unchecked
+/// calls are reported beforehand, so that they are not blamed on this one.
+macro_rules! verify(
+    ($e: expr) => {
+        {
+            ::graphics::gl_error::check_previous_errors(concat!(file!(), ":", line!()));
+            let result = $e;
+            ::graphics::gl_error::check_errors(concat!(file!(), ":", line!(), ": ", stringify!($e)));
+            result
+        }
+    }
+);
+
+pub use self::asset::{ Asset, AssetPart, Buffer };
+pub use self::asset_manager::AssetManager;
 pub use self::camera::Camera;
+pub use self::framebuffer::{ Framebuffer, Screenshot };
+pub use self::gl_error::ErrorMode;
+pub use self::material::Material;
 pub use self::object::Object;
-pub use self::graphics_engine::{ Asset, Buffer, GraphicsEngine };
+pub use self::object_pool::{ ObjectHandle, ObjectPool };
+pub use self::shader_program::ShaderProgram;
+pub use self::texture::Texture;
+pub use self::graphics_engine::GraphicsEngine;
+pub use self::normals::NormalGeneration;
+pub use self::utils::{ ImportError, ImportErrorKind, ImportOptions, Mesh, MeshPart };
 
+mod asset;
+mod asset_manager;
 mod camera;
+mod file_watcher;
+mod framebuffer;
+pub mod gl_error;
 mod object;
+pub mod object_pool;
 mod graphics_engine;
+mod material;
+mod shader_program;
+mod texture;
+pub mod matrix;
+mod normals;
+mod triangulation;
 pub mod utils;
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::f32;
+use std::mem;
+use std::num::Float;
+
+/// How vertex normals are generated for faces which do not specify them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalGeneration {
+    /// Every face is lit uniformly using its own normal.
+    Flat,
+    /// Normals are averaged across neighbouring faces weighted by the angle
+    /// each face subtends at the vertex. Faces meeting at an angle (in
+    /// radians) greater than `crease_angle` are not smoothed together.
+    Smooth { crease_angle: f32 },
+}
+
+
+/// Generates a normal for every triangle corner in `indices`, returning the
+/// normals and an index into them for each corner.
+pub fn generate_normals(vertices: &Vec<[f32; 3]>, indices: &Vec<u32>, mode: NormalGeneration) -> (Vec<[f32; 3]>, Vec<u32>) {
+    let num_triangles = indices.len() / 3;
+    let face_normals: Vec<[f32; 3]> = range(0us, num_triangles).map(|t| {
+        let (a, b, c) = triangle(vertices, indices, t);
+        normalize(cross(sub(b, a), sub(c, a)))
+    }).collect();
+
+    match mode {
+        NormalGeneration::Flat => {
+            let normal_indices = range(0us, indices.len()).map(|corner| (corner / 3) as u32).collect();
+
+            return (face_normals, normal_indices);
+        }
+
+        NormalGeneration::Smooth { crease_angle } => {
+            let min_cosine = crease_angle.cos();
+
+            // triangles sharing each vertex
+            let mut adjacency: Vec<Vec<usize>> = range(0us, vertices.len()).map(|_| Vec::new()).collect();
+            for (corner, &vertex) in indices.iter().enumerate() {
+                adjacency[vertex as usize].push(corner / 3);
+            }
+
+            let mut normals: Vec<[f32; 3]> = Vec::new();
+            let mut normal_indices: Vec<u32> = Vec::new();
+            let mut existing: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+
+            for (corner, &vertex) in indices.iter().enumerate() {
+                let face_normal = face_normals[corner / 3];
+                let mut sum = [0.0f32; 3];
+
+                for &t in adjacency[vertex as usize].iter() {
+                    if dot(face_normal, face_normals[t]) < min_cosine {
+                        continue;
+                    }
+
+                    let weight = corner_angle(vertices, indices, t, vertex);
+                    sum = add(sum, scale(face_normals[t], weight));
+                }
+
+                let normal = normalize(sum);
+                // corners which end up with the same normal share a single entry
+                let key = (vertex, unsafe { mem::transmute::<[f32; 3], [u32; 3]>(normal) });
+
+                let index = match existing.get(&key) {
+                    Some(&index) => index,
+                    None => {
+                        normals.push(normal);
+                        normals.len() as u32 - 1
+                    }
+                };
+
+                existing.insert(key, index);
+                normal_indices.push(index);
+            }
+
+            return (normals, normal_indices);
+        }
+    }
+}
+
+
+fn triangle(vertices: &Vec<[f32; 3]>, indices: &Vec<u32>, t: usize) -> ([f32; 3], [f32; 3], [f32; 3]) {
+    (vertices[indices[3*t] as usize], vertices[indices[3*t + 1] as usize], vertices[indices[3*t + 2] as usize])
+}
+
+
+/// The interior angle of triangle `t` at the given vertex.
+fn corner_angle(vertices: &Vec<[f32; 3]>, indices: &Vec<u32>, t: usize, vertex: u32) -> f32 {
+    let corner = range(3*t, 3*t + 3).find(|&c| indices[c] == vertex).unwrap();
+    let next = 3*t + (corner + 1) % 3;
+    let prev = 3*t + (corner + 2) % 3;
+
+    let p = vertices[vertex as usize];
+    let u = normalize(sub(vertices[indices[next] as usize], p));
+    let v = normalize(sub(vertices[indices[prev] as usize], p));
+
+    dot(u, v).max(-1.0).min(1.0).acos()
+}
+
+
+#[inline]
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+
+#[inline]
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+
+#[inline]
+fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+
+#[inline]
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0]*b[0] + a[1]*b[1] + a[2]*b[2]
+}
+
+
+#[inline]
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1]*b[2] - a[2]*b[1], a[2]*b[0] - a[0]*b[2], a[0]*b[1] - a[1]*b[0]]
+}
+
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+
+    if length < f32::EPSILON {
+        return [0.0; 3];
+    }
+
+    scale(a, 1.0 / length)
+}
+
+
+#[test]
+fn generate_normals_test() {
+    fn approx_eq(a: [f32; 3], b: [f32; 3]) -> bool {
+        range(0us, 3us).all(|i| (a[i] - b[i]).abs() < 1e-6)
+    }
+
+    // two faces of a cube meeting at a right angle along the edge 0-1
+    let vertices = vec!(
+        [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+        [1.0, 0.0, -1.0], [0.0, 0.0, -1.0],
+    );
+    let indices = vec!(
+        0, 1, 2, 0, 2, 3,
+        1, 0, 5, 1, 5, 4,
+    );
+
+    let (normals, normal_indices) = generate_normals(&vertices, &indices, NormalGeneration::Flat);
+    assert_eq!(normals.len(), 4);
+    assert_eq!(normal_indices.len(), indices.len());
+    assert!(approx_eq(normals[normal_indices[0] as usize], [0.0, 0.0, 1.0]));
+    assert!(approx_eq(normals[normal_indices[6] as usize], [0.0, -1.0, 0.0]));
+
+    // within the crease angle the shared edge is smoothed
+    let (normals, normal_indices) = generate_normals(&vertices, &indices, NormalGeneration::Smooth { crease_angle: f32::consts::PI });
+    assert_eq!(normal_indices[0], normal_indices[7]);
+    let n = normals[normal_indices[0] as usize];
+    assert!(approx_eq(n, [0.0, -0.5f32.sqrt(), 0.5f32.sqrt()]));
+
+    // beyond the crease angle each side keeps its own normal
+    let (normals, normal_indices) = generate_normals(&vertices, &indices, NormalGeneration::Smooth { crease_angle: f32::consts::PI / 4.0 });
+    assert!(normal_indices[0] != normal_indices[7]);
+    assert!(approx_eq(normals[normal_indices[0] as usize], [0.0, 0.0, 1.0]));
+    assert!(approx_eq(normals[normal_indices[7] as usize], [0.0, -1.0, 0.0]));
+    // corners on the same flat face share their normal
+    assert_eq!(normal_indices[0], normal_indices[3]);
+}
@@ -1,10 +1,23 @@
+extern crate mithril;
+
 use graphics;
+use std::default::Default;
 use std::rc::Rc;
+use std::num::Float;
+use self::mithril::math::{ Vector, Quaternion };
 
 pub struct Object<'a> {
     asset: Rc<graphics::Asset<'a>>,
+    part: Option<String>,
+    texture: Option<Rc<graphics::Texture>>,
+    material: Option<graphics::Material>,
     translation: [f32; 3],
-    scale: f32,
+    rotation: Quaternion,
+    previous_translation: [f32; 3],
+    previous_rotation: Quaternion,
+    scale: [f32; 3],
+    origin: Option<[f32; 3]>,
+    body_id: Option<usize>,
 }
 
 
@@ -12,8 +25,16 @@ impl<'a> Object<'a> {
     pub fn new(asset: Rc<graphics::Asset<'a>>) -> Object<'a> {
         Object{
             asset: asset,
+            part: None,
+            texture: None,
+            material: None,
             translation: [0.0; 3],
-            scale: 1.0,
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            previous_translation: [0.0; 3],
+            previous_rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            scale: [1.0; 3],
+            origin: None,
+            body_id: None,
         }
     }
 
@@ -24,25 +45,227 @@ impl<'a> Object<'a> {
     }
 
 
+    /// The name of the only part of the asset drawn for this object, if any.
+    #[inline]
+    pub fn part(&self) -> Option<&str> {
+        self.part.as_ref().map(|name| name.as_slice())
+    }
+
+
+    /// Restricts the object to a single named object or group of its asset,
+    /// or draws the whole asset if `None`.
+    #[inline]
+    pub fn set_part(&mut self, part: Option<String>) {
+        self.part = part;
+    }
+
+
+    #[inline]
+    pub fn texture(&self) -> Option<&graphics::Texture> {
+        self.texture.as_ref().map(|texture| &**texture)
+    }
+
+
+    /// Sets the texture used to shade the object, only has an effect if the
+    /// object's asset has texture coordinates.
     #[inline]
-    pub fn set_scale(&mut self, scale: f32) {
-        self.scale = scale;
+    pub fn set_texture(&mut self, texture: Option<Rc<graphics::Texture>>) {
+        self.texture = texture;
     }
 
 
+    #[inline]
+    pub fn material(&self) -> Option<&graphics::Material> {
+        self.material.as_ref()
+    }
+
+
+    /// Overrides the materials of every part of the object's asset, or
+    /// restores them if `None`.
+    #[inline]
+    pub fn set_material(&mut self, material: Option<graphics::Material>) {
+        self.material = material;
+    }
+
+
+    /// Sets the base colour of the object, overriding the asset's materials.
+    pub fn set_color(&mut self, r: f32, g: f32, b: f32) {
+        match self.material {
+            Some(ref mut material) => {
+                material.diffuse = [r, g, b];
+                return;
+            }
+
+            None => { /* do nothing */ }
+        }
+
+        self.material = Some(graphics::Material{ diffuse: [r, g, b], .. Default::default() });
+    }
+
+
+    #[inline]
+    pub fn body_id(&self) -> Option<usize> {
+        self.body_id
+    }
+
+
+    /// Attaches the object to a body in the simulation, its transform will be
+    /// overwritten with the body's position and orientation on every update.
+    #[inline]
+    pub fn bind_to_body(&mut self, body_id: usize) {
+        self.body_id = Some(body_id);
+    }
+
+
+    #[inline]
+    pub fn unbind_body(&mut self) {
+        self.body_id = None;
+    }
+
+
+    #[inline]
+    pub fn translation(&self) -> [f32; 3] {
+        self.translation
+    }
+
+
+    #[inline]
+    pub fn rotation(&self) -> Quaternion {
+        self.rotation
+    }
+
+
+    #[inline]
+    pub fn scale(&self) -> [f32; 3] {
+        self.scale
+    }
+
+
+    /// Scales the object along its local axes, before it is rotated.
+    #[inline]
+    pub fn set_scale(&mut self, x: f32, y: f32, z: f32) {
+        self.scale = [x, y, z];
+    }
+
+
+    #[inline]
+    pub fn set_uniform_scale(&mut self, scale: f32) {
+        self.scale = [scale; 3];
+    }
+
+
+    /// The point of the asset which is placed at the object's translation.
+    /// Unless it has been set, this is the origin of the object's part, read
+    /// from the asset so that it follows the part when the asset is reloaded.
+    pub fn origin(&self) -> [f32; 3] {
+        match self.origin {
+            Some(origin) => origin,
+
+            None => {
+                self.part.as_ref()
+                    .and_then(|name| self.asset.part_origin(name.as_slice()))
+                    .unwrap_or([0.0; 3])
+            }
+        }
+    }
+
+
+    /// Sets the point of the asset which is placed at the object's
+    /// translation, and about which the object is rotated and scaled.
+    #[inline]
+    pub fn set_origin(&mut self, x: f32, y: f32, z: f32) {
+        self.origin = Some([x, y, z]);
+    }
+
+
+    /// Moves the object without interpolating from its previous position.
     #[inline]
     pub fn set_translation(&mut self, x: f32, y: f32, z: f32) {
         self.translation = [x, y, z];
+        self.previous_translation = self.translation;
+    }
+
+
+    /// Rotates the object without interpolating from its previous orientation.
+    #[inline]
+    pub fn set_rotation(&mut self, rotation: Quaternion) {
+        self.rotation = rotation;
+        self.previous_rotation = rotation;
+    }
+
+
+    /// Rotates the object by `angle` radians about the axis `(x, y, z)`.
+    #[inline]
+    pub fn set_rotation_from_axis_angle(&mut self, angle: f32, x: f32, y: f32, z: f32) {
+        self.set_rotation(Quaternion::new_from_rotation(angle, x, y, z));
+    }
+
+
+    /// Records the latest simulated state of the body, keeping the previous
+    /// state around so that rendering can interpolate between the two.
+    pub fn set_body_transform(&mut self, position: Vector, rotation: Quaternion) {
+        self.previous_translation = self.translation;
+        self.previous_rotation = self.rotation;
+        self.translation = [position[0], position[1], position[2]];
+        self.rotation = rotation;
     }
 
 
     #[inline]
     pub fn model_matrix(&self) -> [f32; 16] {
-        [
-            self.scale,        0.0,        0.0, self.translation[0],
-                   0.0, self.scale,        0.0, self.translation[1],
-                   0.0,        0.0, self.scale, self.translation[2],
-                   0.0,        0.0,        0.0,                 1.0,
-        ]
+        transformation_matrix(self.translation, self.rotation, self.scale, self.origin())
+    }
+
+
+    /// Computes the model matrix at a fraction `alpha` of the way between the
+    /// previous and current state of the object.
+    pub fn interpolated_model_matrix(&self, alpha: f32) -> [f32; 16] {
+        let translation = [
+            self.previous_translation[0] + (self.translation[0] - self.previous_translation[0]) * alpha,
+            self.previous_translation[1] + (self.translation[1] - self.previous_translation[1]) * alpha,
+            self.previous_translation[2] + (self.translation[2] - self.previous_translation[2]) * alpha,
+        ];
+
+        transformation_matrix(translation, nlerp(self.previous_rotation, self.rotation, alpha), self.scale, self.origin())
+    }
+}
+
+
+/// Builds the row-major matrix `T * R * S * O`, moving the origin to zero
+/// first, then scaling, rotating and finally translating.
+fn transformation_matrix(translation: [f32; 3], rotation: Quaternion, scale: [f32; 3], origin: [f32; 3]) -> [f32; 16] {
+    let (w, x, y, z) = (rotation[0], rotation[1], rotation[2], rotation[3]);
+    let (sx, sy, sz) = (scale[0], scale[1], scale[2]);
+
+    let mut matrix = [
+        sx*(1.0 - 2.0*(y*y + z*z)),       sy*(2.0*(x*y - w*z)),       sz*(2.0*(x*z + w*y)), translation[0],
+              sx*(2.0*(x*y + w*z)), sy*(1.0 - 2.0*(x*x + z*z)),       sz*(2.0*(y*z - w*x)), translation[1],
+              sx*(2.0*(x*z - w*y)),       sy*(2.0*(y*z + w*x)), sz*(1.0 - 2.0*(x*x + y*y)), translation[2],
+                               0.0,                        0.0,                        0.0,            1.0,
+    ];
+
+    for row in range(0us, 3us) {
+        let offset = matrix[4*row]*origin[0] + matrix[4*row + 1]*origin[1] + matrix[4*row + 2]*origin[2];
+        matrix[4*row + 3] = matrix[4*row + 3] - offset;
+    }
+
+    return matrix;
+}
+
+
+/// Normalized linear interpolation between two rotations, always taking the
+/// shortest path. Close enough to a slerp for the small angles covered in a
+/// single time step.
+fn nlerp(from: Quaternion, to: Quaternion, alpha: f32) -> Quaternion {
+    let dot = from[0]*to[0] + from[1]*to[1] + from[2]*to[2] + from[3]*to[3];
+    let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+
+    let mut q = [0.0f32; 4];
+    for i in range(0us, 4us) {
+        q[i] = from[i] * (1.0 - alpha) + sign * to[i] * alpha;
     }
+
+    let length = (q[0]*q[0] + q[1]*q[1] + q[2]*q[2] + q[3]*q[3]).sqrt();
+
+    Quaternion::new(q[0]/length, q[1]/length, q[2]/length, q[3]/length)
 }
@@ -0,0 +1,216 @@
+use std::slice;
+use std::u32;
+
+/// Identifies an object in an `ObjectPool`. A handle is never reused, once its
+/// object has been removed the handle no longer refers to anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectHandle {
+    index: usize,
+    generation: u32,
+}
+
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+
+/// Stores objects in slots that are recycled once their objects are removed,
+/// each slot counts its generations so stale handles can be detected. A slot
+/// which has run out of generations is retired rather than wrapping around.
+pub struct ObjectPool<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<usize>,
+    num_retired_slots: usize,
+}
+
+impl<T> ObjectPool<T> {
+    pub fn new() -> ObjectPool<T> {
+        ObjectPool{
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            num_retired_slots: 0,
+        }
+    }
+
+
+    pub fn insert(&mut self, value: T) -> ObjectHandle {
+        match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.generation = slot.generation + 1;
+                slot.value = Some(value);
+
+                return ObjectHandle{ index: index, generation: slot.generation };
+            }
+
+            None => {
+                self.slots.push(Slot{ generation: 0, value: Some(value) });
+
+                return ObjectHandle{ index: self.slots.len() - 1, generation: 0 };
+            }
+        }
+    }
+
+
+    /// Removes the object, returning it or `None` if the handle is stale.
+    pub fn remove(&mut self, handle: ObjectHandle) -> Option<T> {
+        if !self.contains(handle) {
+            return None;
+        }
+
+        self.release_slot(handle.index);
+
+        return self.slots[handle.index].value.take();
+    }
+
+
+    /// Removes every object, invalidating all existing handles.
+    pub fn clear(&mut self) {
+        for index in range(0, self.slots.len()) {
+            if self.slots[index].value.take().is_some() {
+                self.release_slot(index);
+            }
+        }
+    }
+
+
+    /// Makes an emptied slot available again, unless its generation can not
+    /// be incremented any further.
+    fn release_slot(&mut self, index: usize) {
+        if self.slots[index].generation == u32::MAX {
+            self.num_retired_slots = self.num_retired_slots + 1;
+        } else {
+            self.free_slots.push(index);
+        }
+    }
+
+
+    pub fn contains(&self, handle: ObjectHandle) -> bool {
+        self.get(handle).is_some()
+    }
+
+
+    pub fn get(&self, handle: ObjectHandle) -> Option<&T> {
+        match self.slots.get(handle.index) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_ref(),
+            _ => None,
+        }
+    }
+
+
+    pub fn get_mut(&mut self, handle: ObjectHandle) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_mut(),
+            _ => None,
+        }
+    }
+
+
+    /// The number of objects in the pool.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len() - self.num_retired_slots
+    }
+
+
+    pub fn iter(&self) -> Iter<T> {
+        Iter{ index: 0, slots: self.slots.iter() }
+    }
+
+
+    pub fn iter_mut(&mut self) -> IterMut<T> {
+        IterMut{ index: 0, slots: self.slots.iter_mut() }
+    }
+}
+
+
+/// Iterates over the objects of a pool along with their handles.
+pub struct Iter<'a, T: 'a> {
+    index: usize,
+    slots: slice::Iter<'a, Slot<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (ObjectHandle, &'a T);
+
+    fn next(&mut self) -> Option<(ObjectHandle, &'a T)> {
+        loop {
+            let slot = match self.slots.next() {
+                Some(slot) => slot,
+                None => return None,
+            };
+
+            let handle = ObjectHandle{ index: self.index, generation: slot.generation };
+            self.index = self.index + 1;
+
+            match slot.value {
+                Some(ref value) => return Some((handle, value)),
+                None => { /* do nothing */ }
+            }
+        }
+    }
+}
+
+
+/// Mutably iterates over the objects of a pool along with their handles.
+pub struct IterMut<'a, T: 'a> {
+    index: usize,
+    slots: slice::IterMut<'a, Slot<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (ObjectHandle, &'a mut T);
+
+    fn next(&mut self) -> Option<(ObjectHandle, &'a mut T)> {
+        loop {
+            let slot = match self.slots.next() {
+                Some(slot) => slot,
+                None => return None,
+            };
+
+            let handle = ObjectHandle{ index: self.index, generation: slot.generation };
+            self.index = self.index + 1;
+
+            match slot.value {
+                Some(ref mut value) => return Some((handle, value)),
+                None => { /* do nothing */ }
+            }
+        }
+    }
+}
+
+
+#[test]
+fn object_pool_test() {
+    let mut pool = ObjectPool::new();
+    let a = pool.insert("a");
+    let b = pool.insert("b");
+
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.remove(a), Some("a"));
+    assert_eq!(pool.remove(a), None);
+    assert_eq!(pool.get(a), None);
+
+    // the slot is reused, but the stale handle must not see the new object
+    let c = pool.insert("c");
+    assert!(c != a);
+    assert_eq!(pool.get(a), None);
+    assert_eq!(pool.get(c), Some(&"c"));
+    assert_eq!(pool.iter().map(|(_, value)| *value).collect::<Vec<&str>>(), vec!["c", "b"]);
+
+    pool.clear();
+    assert_eq!(pool.len(), 0);
+    assert_eq!(pool.get(b), None);
+
+    // a slot on its last generation is retired instead of wrapping around
+    let d = pool.insert("d");
+    pool.slots[d.index].generation = u32::MAX;
+    let d = ObjectHandle{ index: d.index, generation: u32::MAX };
+    assert_eq!(pool.remove(d), Some("d"));
+    assert_eq!(pool.len(), 0);
+
+    let e = pool.insert("e");
+    assert!(e.index != d.index);
+    assert_eq!(pool.len(), 1);
+}
@@ -0,0 +1,315 @@
+extern crate gl;
+
+use std::cmp;
+use std::io;
+use std::iter;
+use std::ptr;
+use std::collections::HashMap;
+use std::ffi::CString;
+use gl::types::*;
+
+/// A linked vertex and fragment shader pair. The active uniforms and
+/// attributes are looked up once after linking, so values can be set by name.
+/// The program and its shaders are deleted when it is dropped.
+pub struct ShaderProgram {
+    id: GLuint,
+    vertex_shader_id: GLuint,
+    fragment_shader_id: GLuint,
+    uniforms: HashMap<String, GLint>,
+    attributes: HashMap<String, GLint>,
+}
+
+impl ShaderProgram {
+    /// Compiles and links the shaders, binding each of the named vertex
+    /// attributes to the location matching its position in `attributes`.
+    /// Returns the compile or link log on failure.
+    pub fn new(vertex_shader_source: &str, fragment_shader_source: &str, attributes: &[&str]) -> Result<ShaderProgram, String> {
+        ShaderProgram::build(("vertex shader", vertex_shader_source), ("fragment shader", fragment_shader_source), attributes)
+    }
+
+
+    /// Reads the shader sources from files on disk, compile errors are
+    /// prefixed with the offending file.
+    pub fn new_from_files(vertex_shader_path: &str, fragment_shader_path: &str, attributes: &[&str]) -> Result<ShaderProgram, String> {
+        let vertex_shader_source = try!(read_source(vertex_shader_path));
+        let fragment_shader_source = try!(read_source(fragment_shader_path));
+
+        ShaderProgram::build((vertex_shader_path, vertex_shader_source.as_slice()), (fragment_shader_path, fragment_shader_source.as_slice()), attributes)
+    }
+
+
+    /// Builds the program from `(name, source)` pairs, the names identify the
+    /// shaders in error messages.
+    fn build(vertex_shader: (&str, &str), fragment_shader: (&str, &str), attributes: &[&str]) -> Result<ShaderProgram, String> {
+        let vertex_shader_id = try!(compile_shader(gl::VERTEX_SHADER, vertex_shader.1)
+            .map_err(|log| format!("{}: {}", vertex_shader.0, log)));
+
+        let fragment_shader_id = match compile_shader(gl::FRAGMENT_SHADER, fragment_shader.1) {
+            Ok(shader_id) => shader_id,
+
+            Err(log) => {
+                unsafe { gl::DeleteShader(vertex_shader_id); }
+                return Err(format!("{}: {}", fragment_shader.0, log));
+            }
+        };
+
+        let program_id = match link_program(vertex_shader_id, fragment_shader_id, attributes) {
+            Ok(program_id) => program_id,
+
+            Err(log) => {
+                unsafe {
+                    gl::DeleteShader(fragment_shader_id);
+                    gl::DeleteShader(vertex_shader_id);
+                }
+
+                return Err(format!("failed to link {} and {}: {}", vertex_shader.0, fragment_shader.0, log));
+            }
+        };
+
+        let mut program = ShaderProgram{
+            id: program_id,
+            vertex_shader_id: vertex_shader_id,
+            fragment_shader_id: fragment_shader_id,
+            uniforms: HashMap::new(),
+            attributes: HashMap::new(),
+        };
+        program.find_variables();
+
+        return Ok(program);
+    }
+
+
+    fn find_variables(&mut self) {
+        let program_id = self.id;
+
+        unsafe {
+            let mut count = 0;
+            let mut max_length = 0;
+
+            gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+            for index in range(0, count as GLuint) {
+                let name = active_variable_name(max_length, |length, size, variable_type, buffer| {
+                    gl::GetActiveUniform(program_id, index, max_length, length, size, variable_type, buffer);
+                });
+                let name_c_str = CString::from_slice(name.as_bytes());
+                let location = gl::GetUniformLocation(program_id, name_c_str.as_ptr());
+
+                self.uniforms.insert(name, location);
+            }
+
+            gl::GetProgramiv(program_id, gl::ACTIVE_ATTRIBUTES, &mut count);
+            gl::GetProgramiv(program_id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+
+            for index in range(0, count as GLuint) {
+                let name = active_variable_name(max_length, |length, size, variable_type, buffer| {
+                    gl::GetActiveAttrib(program_id, index, max_length, length, size, variable_type, buffer);
+                });
+                let name_c_str = CString::from_slice(name.as_bytes());
+                let location = gl::GetAttribLocation(program_id, name_c_str.as_ptr());
+
+                self.attributes.insert(name, location);
+            }
+        }
+    }
+
+
+    #[inline]
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+
+    /// Makes this the current program, the setters only work on the current
+    /// program.
+    pub fn use_program(&self) {
+        unsafe {
+            gl::UseProgram(self.id);
+        }
+    }
+
+
+    /// The location of the active uniform, `None` if the program has no such
+    /// uniform or the compiler optimized it away.
+    pub fn uniform_location(&self, name: &str) -> Option<GLint> {
+        self.uniforms.get(name).map(|location| *location)
+    }
+
+
+    pub fn attribute_location(&self, name: &str) -> Option<GLuint> {
+        self.attributes.get(name).map(|location| *location as GLuint)
+    }
+
+
+    /// The names of the active uniforms.
+    pub fn uniform_names(&self) -> Vec<&str> {
+        self.uniforms.keys().map(|name| name.as_slice()).collect()
+    }
+
+
+    /// The names of the active attributes.
+    pub fn attribute_names(&self) -> Vec<&str> {
+        self.attributes.keys().map(|name| name.as_slice()).collect()
+    }
+
+
+    /// Sets an `int`, `bool` or sampler uniform. Like all setters, this does
+    /// nothing and returns `false` if the program has no such uniform.
+    pub fn set_int(&self, name: &str, value: i32) -> bool {
+        self.set_uniform(name, |location| unsafe { gl::Uniform1i(location, value) })
+    }
+
+
+    pub fn set_bool(&self, name: &str, value: bool) -> bool {
+        self.set_int(name, if value { 1 } else { 0 })
+    }
+
+
+    pub fn set_float(&self, name: &str, value: f32) -> bool {
+        self.set_uniform(name, |location| unsafe { gl::Uniform1f(location, value) })
+    }
+
+
+    pub fn set_vec3(&self, name: &str, value: &[f32; 3]) -> bool {
+        self.set_uniform(name, |location| unsafe { gl::Uniform3fv(location, 1, &value[0]) })
+    }
+
+
+    pub fn set_vec4(&self, name: &str, value: &[f32; 4]) -> bool {
+        self.set_uniform(name, |location| unsafe { gl::Uniform4fv(location, 1, &value[0]) })
+    }
+
+
+    /// Sets a `mat3` uniform from a row-major matrix.
+    pub fn set_mat3(&self, name: &str, value: &[f32; 9]) -> bool {
+        self.set_uniform(name, |location| unsafe { gl::UniformMatrix3fv(location, 1, gl::TRUE, &value[0]) })
+    }
+
+
+    /// Sets a `mat4` uniform from a row-major matrix.
+    pub fn set_mat4(&self, name: &str, value: &[f32; 16]) -> bool {
+        self.set_uniform(name, |location| unsafe { gl::UniformMatrix4fv(location, 1, gl::TRUE, &value[0]) })
+    }
+
+
+    fn set_uniform<F: FnOnce(GLint)>(&self, name: &str, set: F) -> bool {
+        match self.uniform_location(name) {
+            Some(location) => {
+                set(location);
+                return true;
+            }
+
+            None => return false,
+        }
+    }
+}
+
+impl Drop for ShaderProgram {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+            gl::DeleteShader(self.fragment_shader_id);
+            gl::DeleteShader(self.vertex_shader_id);
+        }
+    }
+}
+
+
+/// Reads the name of an active uniform or attribute through `get_active`,
+/// which is given the pointers to write the name's length, the variable's
+/// size and type, and the name itself to. Uniform arrays are named after their
+/// first element, `lights[0]`, which is stored as `lights`.
+unsafe fn active_variable_name<F>(max_length: GLint, get_active: F) -> String
+        where F: FnOnce(*mut GLsizei, *mut GLint, *mut GLenum, *mut GLchar) {
+    let mut buffer: Vec<u8> = iter::repeat(0u8).take(max_length as usize).collect();
+    let mut length: GLsizei = 0;
+    let mut size: GLint = 0;
+    let mut variable_type: GLenum = 0;
+
+    get_active(&mut length, &mut size, &mut variable_type, buffer.as_mut_ptr() as *mut GLchar);
+    buffer.truncate(length as usize);
+
+    let name = String::from_utf8_lossy(buffer.as_slice()).into_owned();
+
+    if name.ends_with("[0]") {
+        return name.as_slice().slice_to(name.len() - 3).to_string();
+    }
+
+    return name;
+}
+
+
+fn read_source(filepath: &str) -> Result<String, String> {
+    io::File::open(&Path::new(filepath))
+        .and_then(|mut file| file.read_to_string())
+        .map_err(|err| format!("{}: {}", filepath, err))
+}
+
+
+/// Compiles the shader, returning the compile log if it fails.
+fn compile_shader(shader_type: GLenum, shader_source: &str) -> Result<GLuint, String> {
+    let shader_source_c_str = CString::from_slice(shader_source.as_bytes());
+
+    unsafe {
+        let shader = gl::CreateShader(shader_type);
+
+        gl::ShaderSource(shader, 1, &shader_source_c_str.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut status = gl::FALSE as GLint;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+
+        if status != (gl::TRUE as GLint) {
+            let mut len = 0;
+            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+            // the length includes the terminating NUL, and is 0 without a log
+            let mut buffer: Vec<u8> = iter::repeat(0u8).take(cmp::max(len, 1) as usize).collect();
+            let mut written = 0;
+            gl::GetShaderInfoLog(shader, buffer.len() as GLsizei, &mut written, buffer.as_mut_ptr() as *mut GLchar);
+            buffer.truncate(written as usize);
+            gl::DeleteShader(shader);
+
+            return Err(String::from_utf8_lossy(buffer.as_slice()).into_owned());
+        }
+
+        return Ok(shader);
+    }
+}
+
+
+/// Links the shaders into a program, binding each of the named vertex
+/// attributes to the location matching its position in `attributes`. Returns
+/// the link log if it fails.
+fn link_program(vertex_shader_id: GLuint, fragment_shader_id: GLuint, attributes: &[&str]) -> Result<GLuint, String> {
+    unsafe {
+        let program_id = gl::CreateProgram();
+        gl::AttachShader(program_id, vertex_shader_id);
+        gl::AttachShader(program_id, fragment_shader_id);
+
+        for (location, attribute) in attributes.iter().enumerate() {
+            let attribute_name = CString::from_slice(attribute.as_bytes());
+            gl::BindAttribLocation(program_id, location as GLuint, attribute_name.as_ptr());
+        }
+
+        gl::LinkProgram(program_id);
+
+        let mut status = gl::FALSE as GLint;
+        gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut status);
+
+        if status != (gl::TRUE as GLint) {
+            let mut len = 0;
+            gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut len);
+            // the length includes the terminating NUL, and is 0 without a log
+            let mut buffer: Vec<u8> = iter::repeat(0u8).take(cmp::max(len, 1) as usize).collect();
+            let mut written = 0;
+            gl::GetProgramInfoLog(program_id, buffer.len() as GLsizei, &mut written, buffer.as_mut_ptr() as *mut GLchar);
+            buffer.truncate(written as usize);
+            gl::DeleteProgram(program_id);
+
+            return Err(String::from_utf8_lossy(buffer.as_slice()).into_owned());
+        }
+
+        return Ok(program_id);
+    }
+}
@@ -0,0 +1,68 @@
+extern crate gl;
+extern crate image;
+
+use std::mem;
+use gl::types::{ GLint, GLuint };
+use self::image::{ GenericImage, ImageResult };
+use self::image::imageops;
+
+pub struct Texture {
+    id: GLuint,
+    width: u32,
+    height: u32,
+}
+
+impl Texture {
+    /// Loads an image from disk (typically a PNG) into a new RGBA texture.
+    pub fn new_from_file(filepath: &str) -> ImageResult<Texture> {
+        let image = try!(image::open(&Path::new(filepath)));
+        // OpenGL expects the first row of texels to be the bottom of the image
+        let pixels = imageops::flip_vertical(&image.to_rgba());
+        let (width, height) = pixels.dimensions();
+        let data = pixels.into_vec();
+        let mut id: GLuint = 0;
+
+        unsafe {
+            gl::GenTextures(1, &mut id as *mut u32);
+            verify!(gl::BindTexture(gl::TEXTURE_2D, id));
+            verify!(gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as GLint,
+                                   width as i32, height as i32, 0,
+                                   gl::RGBA, gl::UNSIGNED_BYTE,
+                                   mem::transmute(&data.as_slice()[0])));
+
+            verify!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint));
+            verify!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint));
+            verify!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint));
+            verify!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint));
+            verify!(gl::GenerateMipmap(gl::TEXTURE_2D));
+
+            verify!(gl::BindTexture(gl::TEXTURE_2D, 0));
+        }
+
+        return Ok(Texture{
+            id: id,
+            width: width,
+            height: height,
+        });
+    }
+
+
+    #[inline]
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::num::Float;
+
+/// Splits a planar polygon into triangles, returning triples of indices into
+/// `polygon` with the same winding as the original polygon. Convex polygons
+/// are split into a fan, concave polygons are triangulated by ear clipping.
+pub fn triangulate(polygon: &[[f32; 3]]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+
+    if n < 3 {
+        return Vec::new();
+    }
+
+    if n == 3 {
+        return vec!([0, 1, 2]);
+    }
+
+    let points = project_to_plane(polygon);
+
+    if is_convex(&points) {
+        let indices: Vec<usize> = range(0us, n).collect();
+        return fan(&indices);
+    }
+
+    return ear_clip(&points);
+}
+
+
+/// Projects the polygon onto the coordinate plane most parallel to it, such
+/// that the projected polygon is wound counter-clockwise.
+fn project_to_plane(polygon: &[[f32; 3]]) -> Vec<[f32; 2]> {
+    // Newell's method, robust against slightly non-planar polygons
+    let mut normal = [0.0f32; 3];
+    for i in range(0us, polygon.len()) {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+
+        normal[0] = normal[0] + (a[1] - b[1]) * (a[2] + b[2]);
+        normal[1] = normal[1] + (a[2] - b[2]) * (a[0] + b[0]);
+        normal[2] = normal[2] + (a[0] - b[0]) * (a[1] + b[1]);
+    }
+
+    let mut axis = 0us;
+    for k in range(1us, 3us) {
+        if normal[k].abs() > normal[axis].abs() {
+            axis = k;
+        }
+    }
+
+    let (u, v) = match axis {
+        0 => (1us, 2us),
+        1 => (2us, 0us),
+        _ => (0us, 1us),
+    };
+
+    let (u, v) = if normal[axis] < 0.0 { (v, u) } else { (u, v) };
+
+    polygon.iter().map(|p| [p[u], p[v]]).collect()
+}
+
+
+#[inline]
+fn cross(o: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+
+fn is_convex(points: &Vec<[f32; 2]>) -> bool {
+    let n = points.len();
+
+    range(0us, n).all(|i| cross(points[i], points[(i + 1) % n], points[(i + 2) % n]) >= 0.0)
+}
+
+
+fn fan(indices: &Vec<usize>) -> Vec<[usize; 3]> {
+    range(1us, indices.len() - 1).map(|i| [indices[0], indices[i], indices[i + 1]]).collect()
+}
+
+
+fn contains_point(a: [f32; 2], b: [f32; 2], c: [f32; 2], p: [f32; 2]) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+
+fn ear_clip(points: &Vec<[f32; 2]>) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = range(0us, points.len()).collect();
+    let mut triangles = Vec::new();
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let mut ear = None;
+
+        for i in range(0us, n) {
+            let (prev, curr, next) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            let (a, b, c) = (points[prev], points[curr], points[next]);
+
+            if cross(a, b, c) <= 0.0 {
+                // reflex or degenerate corner
+                continue;
+            }
+
+            let is_ear = !remaining.iter()
+                .filter(|&&j| j != prev && j != curr && j != next)
+                .any(|&j| contains_point(a, b, c, points[j]));
+
+            if is_ear {
+                ear = Some(i);
+                break;
+            }
+        }
+
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+                remaining.remove(i);
+            }
+
+            None => {
+                // self-intersecting or degenerate polygon, do the best we can
+                triangles.extend(fan(&remaining).into_iter());
+                return triangles;
+            }
+        }
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    return triangles;
+}
+
+
+#[test]
+fn triangulate_test() {
+    fn area(points: &Vec<[f32; 2]>, triangles: &Vec<[usize; 3]>) -> f32 {
+        triangles.iter().fold(0.0, |sum, t| sum + 0.5 * cross(points[t[0]], points[t[1]], points[t[2]]))
+    }
+
+    // convex quad in the xz-plane, facing down
+    let quad = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0]];
+    let triangles = triangulate(&quad);
+    assert_eq!(triangles, vec!([0, 1, 2], [0, 2, 3]));
+    assert_eq!(area(&project_to_plane(&quad), &triangles), 1.0);
+
+    // concave arrow head, a fan from the first vertex would cover the notch
+    let arrow = [[0.0, 0.0, 0.0], [2.0, 1.0, 0.0], [0.0, 2.0, 0.0], [1.0, 1.0, 0.0]];
+    let triangles = triangulate(&arrow);
+    assert_eq!(triangles.len(), 2);
+    assert_eq!(area(&project_to_plane(&arrow), &triangles), 1.0);
+
+    // every triangle keeps the winding of the polygon
+    let points = project_to_plane(&arrow);
+    for t in triangles.iter() {
+        assert!(cross(points[t[0]], points[t[1]], points[t[2]]) > 0.0);
+    }
+}
@@ -1,106 +1,726 @@
 extern crate gl;
-extern crate regex;
 
-use self::regex::Regex;
+use graphics::triangulation;
+use graphics::normals::{ self, NormalGeneration };
+use graphics::Material;
 use gl::types::{ GLfloat, GLuint };
-use std::io::{ BufferedReader, File };
+use std::error::Error;
+use std::collections::HashMap;
+use std::default::Default;
+use std::f32;
+use std::fmt;
+use std::hash::Hash;
+use std::num::Float;
+use std::io::{ Buffer, BufferedReader, File, IoError };
 
-pub fn import_from_obj(filepath: &str) -> (Vec<GLfloat>, Vec<GLfloat>, Vec<GLuint>) {
-    let comments_regex = Regex::new(r"\A\s*#(?s:.*)\z").ok().unwrap();
-    let vertex_regex = Regex::new(r"\A\s*v\s+(\+?-?\d+\.\d+)\s+(\+?-?\d+\.\d+)\s+(\+?-?\d+\.\d+)\s*\z").ok().unwrap();
-    let vertex_normal_regex = Regex::new(r"\A\s*vn\s+(\+?-?\d+\.\d+)\s+(\+?-?\d+\.\d+)\s+(\+?-?\d+\.\d+)\s*\z").ok().unwrap();
-    let face_regex = Regex::new(r"\A\s*f\s+(\d+)/(\d+)?/(\d+)\s+(\d+)/(\d+)?/(\d+)\s+(\d+)/(\d+)?/(\d+)\s*\z").ok().unwrap();
+/// Directives which are valid in an OBJ file but have no effect on the
+/// imported geometry.
+static IGNORED_DIRECTIVES: [&'static str; 13] = [
+    "s", "vp", "mg", "lod", "bevel", "c_interp", "d_interp",
+    "usemap", "maplib", "shadow_obj", "trace_obj", "ctech", "stech",
+];
 
-    let mut file = BufferedReader::new(File::open(&Path::new(filepath)));
+/// Directives which are valid in an OBJ file but describe elements which are
+/// not imported, such as lines and free-form surfaces. These are skipped with
+/// a warning.
+static SKIPPED_DIRECTIVES: [&'static str; 16] = [
+    "l", "p", "curv", "curv2", "surf", "cstype", "deg", "bmat",
+    "step", "parm", "trim", "hole", "scrv", "sp", "end", "con",
+];
+
+/// Directives which are valid in an MTL file but are not used when rendering.
+static IGNORED_MATERIAL_DIRECTIVES: [&'static str; 16] = [
+    "Ka", "Ke", "Ni", "Tf", "illum", "sharpness",
+    "map_Ka", "map_Ks", "map_Ns", "map_d", "map_bump", "map_Bump", "bump", "disp", "decal", "refl",
+];
+
+/// The diffuse colour of materials which do not set `Kd`, white so that
+/// textures are not tinted.
+const DEFAULT_DIFFUSE: [f32; 3] = [1.0; 3];
+
+#[derive(Debug)]
+pub enum ImportErrorKind {
+    Io(IoError),
+    BadNumber(String),
+    IndexOutOfRange(i64),
+    UnsupportedDirective(String),
+    MalformedLine(String),
+    UnknownMaterial(String),
+}
+
+/// An error encountered while importing a mesh, `line` is 1-based and is 0
+/// for errors which are not associated with any particular line.
+#[derive(Debug)]
+pub struct ImportError {
+    pub path: String,
+    pub line: usize,
+    pub kind: ImportErrorKind,
+}
+
+impl ImportError {
+    fn new(path: &str, line: usize, kind: ImportErrorKind) -> ImportError {
+        ImportError{
+            path: path.to_string(),
+            line: line,
+            kind: kind,
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}:{}: ", self.path, self.line));
+
+        match self.kind {
+            ImportErrorKind::Io(ref err) => write!(f, "{}", err),
+            ImportErrorKind::BadNumber(ref token) => write!(f, "could not parse {:?} as a number", token),
+            ImportErrorKind::IndexOutOfRange(index) => write!(f, "index {} is out of range", index),
+            ImportErrorKind::UnsupportedDirective(ref directive) => write!(f, "unsupported directive {:?}", directive),
+            ImportErrorKind::MalformedLine(ref contents) => write!(f, "malformed line {:?}", contents),
+            ImportErrorKind::UnknownMaterial(ref name) => write!(f, "material {:?} is not defined in any material library", name),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn description(&self) -> &str {
+        match self.kind {
+            ImportErrorKind::Io(_) => "I/O error",
+            ImportErrorKind::BadNumber(_) => "bad number",
+            ImportErrorKind::IndexOutOfRange(_) => "index out of range",
+            ImportErrorKind::UnsupportedDirective(_) => "unsupported directive",
+            ImportErrorKind::MalformedLine(_) => "malformed line",
+            ImportErrorKind::UnknownMaterial(_) => "unknown material",
+        }
+    }
+}
+
+/// Options controlling how meshes are imported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImportOptions {
+    /// How normals are generated for faces which do not reference any.
+    pub normals: NormalGeneration,
+}
+
+impl Default for ImportOptions {
+    fn default() -> ImportOptions {
+        ImportOptions{
+            normals: NormalGeneration::Smooth { crease_angle: f32::consts::PI / 3.0 },
+        }
+    }
+}
+
+/// Mesh data ready to be uploaded to the GPU, with a single index per
+/// triangle corner into each of the flattened vertex streams. `uvs` is empty
+/// for meshes without texture coordinates.
+pub struct Mesh {
+    pub vertices: Vec<GLfloat>,
+    pub normals: Vec<GLfloat>,
+    pub uvs: Vec<GLfloat>,
+    pub indices: Vec<GLuint>,
+    pub parts: Vec<MeshPart>,
+    /// The paths of the material libraries referenced by the mesh, including
+    /// those which could not be loaded.
+    pub material_libraries: Vec<String>,
+}
+
+impl Mesh {
+    /// The files the mesh depends on besides the OBJ file itself, that is its
+    /// material libraries and the textures of its materials.
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut dependencies = self.material_libraries.clone();
+
+        for part in self.parts.iter() {
+            match part.material {
+                Some(Material{ diffuse_map: Some(ref texture_path), .. }) => {
+                    if !dependencies.contains(texture_path) {
+                        dependencies.push(texture_path.clone());
+                    }
+                }
+
+                _ => { /* do nothing */ }
+            }
+        }
+
+        return dependencies;
+    }
+}
+
+/// A contiguous range of `Mesh::indices` belonging to a single named object
+/// or group, drawn with a single material.
+pub struct MeshPart {
+    /// The name of the enclosing `o` or `g` directive, `None` for faces
+    /// defined before any.
+    pub name: Option<String>,
+    /// `None` if no material was assigned to the faces.
+    pub material: Option<Material>,
+    pub offset: usize,
+    pub count: usize,
+    /// The centre of the bounding box around every face of the object or
+    /// group, in the coordinates of the file.
+    pub origin: [GLfloat; 3],
+}
+
+impl MeshPart {
+    /// Closes off the last part at `offset` and starts a new one with the same
+    /// name and material, returning the new part. The last part is reused if
+    /// it has no faces.
+    fn begin(parts: &mut Vec<MeshPart>, offset: usize) -> &mut MeshPart {
+        let last = parts.len() - 1;
+
+        if parts[last].offset != offset {
+            parts[last].count = offset - parts[last].offset;
+
+            let part = MeshPart{
+                name: parts[last].name.clone(),
+                material: parts[last].material.clone(),
+                offset: offset,
+                count: 0,
+                origin: [0.0; 3],
+            };
+            parts.push(part);
+        }
+
+        let last = parts.len() - 1;
+
+        &mut parts[last]
+    }
+}
+
+pub fn import_from_obj(filepath: &str, options: &ImportOptions) -> Result<Mesh, ImportError> {
+    let path = Path::new(filepath);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => return Err(ImportError::new(filepath, 0, ImportErrorKind::Io(err))),
+    };
+
+    import_obj_from_reader(&mut BufferedReader::new(file), filepath, Some(&path.dir_path()), options)
+}
+
+
+/// Imports a mesh in the OBJ format from any buffered source. `name`
+/// identifies the source in errors, and material libraries are looked up
+/// relative to `base_dir`. If there is no `base_dir`, `mtllib` directives are
+/// skipped and `usemtl` directives only split the mesh into parts without a
+/// material. Material libraries which fail to load and undefined materials are
+/// reported as warnings, the affected faces are imported without a material.
+/// Lines, points and free-form geometry are skipped with a warning.
+pub fn import_obj_from_reader<R: Buffer>(reader: &mut R, name: &str, base_dir: Option<&Path>, options: &ImportOptions) -> Result<Mesh, ImportError> {
     let mut vertices: Vec<[GLfloat; 3]> = Vec::new();
     let mut normals: Vec<[GLfloat; 3]> = Vec::new();
     let mut indices: Vec<GLuint> = Vec::new();
-    let mut normal_indices: Vec<GLuint> = Vec::new();
+    let mut uvs: Vec<[GLfloat; 2]> = Vec::new();
+    let mut normal_indices: Vec<Option<GLuint>> = Vec::new();
+    let mut uv_indices: Vec<Option<GLuint>> = Vec::new();
+    let mut materials: HashMap<String, Material> = HashMap::new();
+    let mut has_material_library = false;
+    let mut material_libraries: Vec<String> = Vec::new();
+    let mut skipped_directives: Vec<&'static str> = Vec::new();
+    let mut parts: Vec<MeshPart> = vec!(MeshPart{ name: None, material: None, offset: 0, count: 0, origin: [0.0; 3] });
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_num = line_index + 1;
+        let contents = match line {
+            Ok(contents) => contents,
+            Err(err) => return Err(ImportError::new(name, line_num, ImportErrorKind::Io(err))),
+        };
+
+        let tokens = tokenize(contents.as_slice());
+
+        if tokens.is_empty() {
+            continue;
+        }
+
+        let directive = tokens[0];
+
+        if directive == "v" || directive == "vn" {
+            // vertices may have an optional w component, which is ignored
+            let max_tokens = if directive == "v" { 5 } else { 4 };
+            if tokens.len() < 4 || tokens.len() > max_tokens {
+                return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string())));
+            }
+
+            let values = try!(parse_floats(name, line_num, tokens.iter().skip(1).map(|s| *s)));
+            if directive == "v" {
+                vertices.push([values[0], values[1], values[2]]);
+            } else {
+                normals.push([values[0], values[1], values[2]]);
+            }
+            continue;
+        }
+
+        if directive == "vt" {
+            if tokens.len() < 2 || tokens.len() > 4 {
+                return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string())));
+            }
+
+            let uv = try!(parse_floats(name, line_num, tokens.iter().skip(1).map(|s| *s)));
+            // the v coordinate is optional and defaults to 0
+            uvs.push([uv[0], if uv.len() > 1 { uv[1] } else { 0.0 }]);
+            continue;
+        }
+
+        if directive == "f" {
+            if tokens.len() < 4 {
+                return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string())));
+            }
+
+            let mut corners: Vec<(GLuint, Option<GLuint>, Option<GLuint>)> = Vec::new();
+            for token in tokens.iter().skip(1) {
+                corners.push(try!(parse_face_vertex(name, line_num, *token, vertices.len(), uvs.len(), normals.len())));
+            }
+
+            let positions: Vec<[GLfloat; 3]> = corners.iter().map(|&(vertex_index, _, _)| vertices[vertex_index as usize]).collect();
+
+            for triangle in triangulation::triangulate(positions.as_slice()).iter() {
+                for &corner in triangle.iter() {
+                    let (vertex_index, uv_index, normal_index) = corners[corner];
+                    indices.push(vertex_index);
+                    uv_indices.push(uv_index);
+                    normal_indices.push(normal_index);
+                }
+            }
+            continue;
+        }
+
+        if directive == "mtllib" {
+            match base_dir {
+                Some(base_dir) => {
+                    // the mesh is still usable without its materials
+                    for library_name in tokens.iter().skip(1) {
+                        let library_path = base_dir.join(*library_name);
+                        let library_path = library_path.as_str().unwrap_or(*library_name).to_string();
+
+                        match import_from_mtl(library_path.as_slice()) {
+                            Ok(library) => {
+                                materials.extend(library.into_iter());
+                                has_material_library = true;
+                            }
+
+                            Err(err) => println!("Warning: {}", err),
+                        }
+
+                        material_libraries.push(library_path);
+                    }
+                }
+
+                None => { /* do nothing */ }
+            }
+            continue;
+        }
+
+        if directive == "usemtl" {
+            if tokens.len() != 2 {
+                return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string())));
+            }
+
+            let material = materials.get(tokens[1]).map(|material| material.clone());
 
-    for (line_num, line) in file.lines().enumerate() {
-        let contents = line.unwrap();
+            // without any libraries every material is undefined, which is
+            // expected when importing from memory
+            if material.is_none() && has_material_library {
+                println!("Warning: {}", ImportError::new(name, line_num, ImportErrorKind::UnknownMaterial(tokens[1].to_string())));
+            }
+
+            MeshPart::begin(&mut parts, indices.len()).material = material;
+            continue;
+        }
+
+        if directive == "o" || directive == "g" {
+            // a group with several names is identified by all of them
+            let group_name = tokens.slice_from(1).connect(" ");
+            MeshPart::begin(&mut parts, indices.len()).name = if group_name.is_empty() { None } else { Some(group_name) };
+            continue;
+        }
 
-        if comments_regex.is_match(contents.as_slice()) {
+        if IGNORED_DIRECTIVES.contains(&directive) {
             continue;
         }
 
-        match vertex_regex.captures(contents.as_slice()) {
-            Some(capture) => {
-                let vertex: Vec<GLfloat> = capture.iter().skip(1).map(|s| s.parse::<f32>().unwrap()).take(3).collect();
-                vertices.push([vertex[0], vertex[1], vertex[2]]);
-                continue;
+        match SKIPPED_DIRECTIVES.iter().find(|skipped| **skipped == directive) {
+            Some(skipped) => {
+                // warn once per kind of element, files with lines tend to
+                // have a lot of them
+                if !skipped_directives.contains(skipped) {
+                    println!("Warning: {}:{}: skipping {:?} elements, which are not supported", name, line_num, directive);
+                    skipped_directives.push(*skipped);
+                }
             }
 
-            None => { /* do nothing */ }
+            None => {
+                return Err(ImportError::new(name, line_num, ImportErrorKind::UnsupportedDirective(directive.to_string())));
+            }
+        }
+    }
+
+    // corners without a normal take on a generated one instead
+    let normal_indices: Vec<GLuint> = if normal_indices.iter().any(|index| index.is_none()) {
+        let (generated_normals, generated_indices) = normals::generate_normals(&vertices, &indices, options.normals);
+        let offset = normals.len() as GLuint;
+        normals.extend(generated_normals.into_iter());
+
+        normal_indices.iter().zip(generated_indices.iter())
+            .map(|(index, generated_index)| index.unwrap_or(offset + *generated_index))
+            .collect()
+    } else {
+        normal_indices.iter().map(|index| index.unwrap()).collect()
+    };
+
+    MeshPart::begin(&mut parts, indices.len());
+
+    // parts split by materials share the origin of their whole object or
+    // group, so the bounds are merged by name first
+    let mut bounds: HashMap<Option<String>, ([GLfloat; 3], [GLfloat; 3])> = HashMap::new();
+    for part in parts.iter() {
+        let part_bounds = match bounding_box(&vertices, indices.slice(part.offset, part.offset + part.count)) {
+            Some(part_bounds) => part_bounds,
+            None => continue,
+        };
+
+        let merged = match bounds.get(&part.name) {
+            Some(&group_bounds) => merge_bounding_boxes(group_bounds, part_bounds),
+            None => part_bounds,
+        };
+
+        bounds.insert(part.name.clone(), merged);
+    }
+
+    for part in parts.iter_mut() {
+        part.origin = match bounds.get(&part.name) {
+            Some(&(min, max)) => [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0, (min[2] + max[2]) / 2.0],
+            None => [0.0; 3],
+        };
+    }
+
+    let corners: Vec<(GLuint, GLuint, Option<GLuint>)> = range(0us, indices.len())
+        .map(|i| (indices[i], normal_indices[i], uv_indices[i]))
+        .collect();
+    let (unique_corners, new_indices) = unify_corners(&corners);
+    let has_uvs = uv_indices.iter().any(|index| index.is_some());
+
+    let mut mesh = Mesh{
+        vertices: Vec::with_capacity(3 * unique_corners.len()),
+        normals: Vec::with_capacity(3 * unique_corners.len()),
+        uvs: Vec::new(),
+        indices: new_indices,
+        parts: parts.into_iter().filter(|part| part.count > 0).collect(),
+        material_libraries: material_libraries,
+    };
+
+    for &(vertex_index, normal_index, uv_index) in unique_corners.iter() {
+        mesh.vertices.push_all(&vertices[vertex_index as usize]);
+        mesh.normals.push_all(&normals[normal_index as usize]);
+
+        if has_uvs {
+            // corners without texture coordinates in a textured mesh map to the origin
+            mesh.uvs.push_all(&uv_index.map(|i| uvs[i as usize]).unwrap_or([0.0; 2]));
+        }
+    }
+
+    return Ok(mesh);
+}
+
+
+/// Imports the materials defined in an MTL file, keyed by name. Texture paths
+/// are resolved relative to the MTL file.
+pub fn import_from_mtl(filepath: &str) -> Result<HashMap<String, Material>, ImportError> {
+    let path = Path::new(filepath);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) => return Err(ImportError::new(filepath, 0, ImportErrorKind::Io(err))),
+    };
+
+    import_mtl_from_reader(&mut BufferedReader::new(file), filepath, &path.dir_path())
+}
+
+
+/// Imports materials in the MTL format from any buffered source, `name`
+/// identifies the source in errors and texture paths are resolved relative to
+/// `base_dir`.
+pub fn import_mtl_from_reader<R: Buffer>(reader: &mut R, name: &str, base_dir: &Path) -> Result<HashMap<String, Material>, ImportError> {
+    let mut materials: HashMap<String, Material> = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_num = line_index + 1;
+        let contents = match line {
+            Ok(contents) => contents,
+            Err(err) => return Err(ImportError::new(name, line_num, ImportErrorKind::Io(err))),
+        };
+
+        let tokens = tokenize(contents.as_slice());
+
+        if tokens.is_empty() {
+            continue;
         }
 
-        match vertex_normal_regex.captures(contents.as_slice()) {
-            Some(capture) => {
-                let normal: Vec<GLfloat> = capture.iter().skip(1).map(|s| s.parse::<f32>().unwrap()).take(3).collect();
-                normals.push([normal[0], normal[1], normal[2]]);
-                continue;
+        let directive = tokens[0];
+
+        if directive == "newmtl" {
+            if tokens.len() != 2 {
+                return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string())));
+            }
+
+            match current.take() {
+                Some((material_name, material)) => { materials.insert(material_name, material); }
+                None => { /* do nothing */ }
             }
 
-            None => { /* do nothing */ }
+            current = Some((tokens[1].to_string(), Material{ diffuse: DEFAULT_DIFFUSE, .. Default::default() }));
+            continue;
         }
 
-        match face_regex.captures(contents.as_slice()) {
-            Some(capture) => {
-                indices.push(capture.at(1).unwrap().parse::<u32>().unwrap() - 1);
-                indices.push(capture.at(4).unwrap().parse::<u32>().unwrap() - 1);
-                indices.push(capture.at(7).unwrap().parse::<u32>().unwrap() - 1);
+        if IGNORED_MATERIAL_DIRECTIVES.contains(&directive) {
+            continue;
+        }
+
+        let material = match current {
+            Some((_, ref mut material)) => material,
+            None => return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string()))),
+        };
+
+        match directive {
+            "Kd" | "Ks" => {
+                let values = try!(parse_floats(name, line_num, tokens.iter().skip(1).map(|s| *s)));
+                if values.len() != 3 {
+                    return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string())));
+                }
+
+                let color = [values[0], values[1], values[2]];
+                if directive == "Kd" { material.diffuse = color } else { material.specular = color }
+            }
+
+            "Ns" | "d" | "Tr" => {
+                let values = try!(parse_floats(name, line_num, tokens.iter().skip(1).map(|s| *s)));
+                if values.len() != 1 {
+                    return Err(ImportError::new(name, line_num, ImportErrorKind::MalformedLine(contents.as_slice().trim().to_string())));
+                }
+
+                match directive {
+                    "Ns" => material.shininess = values[0],
+                    "d" => material.opacity = values[0],
+                    // transparency, the inverse of dissolve
+                    _ => material.opacity = 1.0 - values[0],
+                }
+            }
 
-                normal_indices.push(capture.at(3).unwrap().parse::<u32>().unwrap() - 1);
-                normal_indices.push(capture.at(6).unwrap().parse::<u32>().unwrap() - 1);
-                normal_indices.push(capture.at(9).unwrap().parse::<u32>().unwrap() - 1);
-                continue;
+            "map_Kd" => {
+                // options preceding the file name are not supported
+                let texture_path = base_dir.join(tokens[tokens.len() - 1]);
+                material.diffuse_map = texture_path.as_str().map(|path| path.to_string());
             }
 
-            None => { /* do nothing */ }
+            _ => {
+                return Err(ImportError::new(name, line_num, ImportErrorKind::UnsupportedDirective(directive.to_string())));
+            }
         }
+    }
+
+    match current {
+        Some((material_name, material)) => { materials.insert(material_name, material); }
+        None => { /* do nothing */ }
+    }
+
+    return Ok(materials);
+}
+
+
+/// Splits a line into whitespace separated tokens, discarding comments.
+fn tokenize(line: &str) -> Vec<&str> {
+    let without_comment = match line.find('#') {
+        Some(index) => line.slice_to(index),
+        None => line,
+    };
+
+    without_comment.split(|c: char| c.is_whitespace()).filter(|s| !s.is_empty()).collect()
+}
+
+
+fn parse_floats<'t, I: Iterator<Item=&'t str>>(filepath: &str, line_num: usize, tokens: I) -> Result<Vec<GLfloat>, ImportError> {
+    let mut values = Vec::new();
+
+    for token in tokens {
+        match parse_float(token) {
+            Some(value) => values.push(value),
+            None => return Err(ImportError::new(filepath, line_num, ImportErrorKind::BadNumber(token.to_string()))),
+        }
+    }
+
+    return Ok(values);
+}
+
 
-        println!("[IGNORED] {:>20}:{:<4} {:?}", filepath, line_num, contents);
+/// Finds the corners of the bounding box around the indexed vertices, `None`
+/// if there are none.
+fn bounding_box(vertices: &Vec<[GLfloat; 3]>, indices: &[GLuint]) -> Option<([GLfloat; 3], [GLfloat; 3])> {
+    if indices.is_empty() {
+        return None;
     }
 
-    let (new_vertices, new_normals, new_indices) = unify_indexes(&indices, &vertices, &normal_indices, &normals);
+    let mut min = vertices[indices[0] as usize];
+    let mut max = min;
+
+    for &index in indices.iter() {
+        let vertex = vertices[index as usize];
 
-    let flattened_vertices: Vec<GLfloat> = new_vertices.iter().flat_map(|a| a.iter().map(|a| *a)).collect();
-    let flattened_normals: Vec<GLfloat> = new_normals.iter().flat_map(|a| a.iter().map(|a| *a)).collect();
+        for axis in range(0us, 3us) {
+            min[axis] = min[axis].min(vertex[axis]);
+            max[axis] = max[axis].max(vertex[axis]);
+        }
+    }
 
-    return (flattened_vertices, flattened_normals, new_indices);
+    return Some((min, max));
 }
 
 
-fn unify_indexes<T: Clone>(indices_0: &Vec<u32>, values_0: &Vec<T>, indices_1: &Vec<u32>, values_1: &Vec<T>) -> (Vec<T>, Vec<T>, Vec<u32>) {
-    let indices: Vec<(u32, u32)> = indices_0.iter().zip(indices_1.iter()).map(|(a, b)| (*a, *b)).collect();
+fn merge_bounding_boxes(a: ([GLfloat; 3], [GLfloat; 3]), b: ([GLfloat; 3], [GLfloat; 3])) -> ([GLfloat; 3], [GLfloat; 3]) {
+    let (mut min, mut max) = a;
+
+    for axis in range(0us, 3us) {
+        min[axis] = min[axis].min((b.0)[axis]);
+        max[axis] = max[axis].max((b.1)[axis]);
+    }
+
+    return (min, max);
+}
+
+
+/// Parses a decimal number of the form `[+-]digits[.digits][(e|E)[+-]digits]`,
+/// where either the integer or the fractional digits may be omitted (`1`,
+/// `.5`, `1.` and `1e-3` are all valid). Returns `None` for anything else.
+fn parse_float(token: &str) -> Option<GLfloat> {
+    let bytes = token.as_bytes();
+    let mut i = 0us;
+
+    fn skip_digits(bytes: &[u8], mut i: usize) -> usize {
+        while i < bytes.len() && bytes[i] >= b'0' && bytes[i] <= b'9' {
+            i = i + 1;
+        }
+
+        return i;
+    }
+
+    if i < bytes.len() && (bytes[i] == b'-' || bytes[i] == b'+') {
+        i = i + 1;
+    }
+
+    let integer_start = i;
+    i = skip_digits(bytes, i);
+    let mut num_digits = i - integer_start;
 
-    let mut available_index = 0u32;
-    let mut consumed: Vec<(u32, u32)> = Vec::new();
-    let mut new_indices: Vec<u32> = Vec::new();
-    let mut new_values_0: Vec<T> = Vec::new();
-    let mut new_values_1: Vec<T> = Vec::new();
+    if i < bytes.len() && bytes[i] == b'.' {
+        let fraction_start = i + 1;
+        i = skip_digits(bytes, fraction_start);
+        num_digits = num_digits + (i - fraction_start);
+    }
 
-    for (index, &index_pair) in indices.iter().enumerate() {
-        let pos = indices.iter().position(|a| index_pair == *a).unwrap();
+    if num_digits == 0 {
+        return None;
+    }
 
-        if pos == index {
-            // first occurrence
-            consumed.push(index_pair);
-            new_indices.push(available_index);
-            new_values_0.push(values_0[index_pair.0 as usize].clone());
-            new_values_1.push(values_1[index_pair.1 as usize].clone());
-            available_index = available_index + 1;
-        } else {
-            // repeated occurrence
-            new_indices.push(consumed.iter().position(|a| index_pair == *a).unwrap() as u32);
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        i = i + 1;
+
+        if i < bytes.len() && (bytes[i] == b'-' || bytes[i] == b'+') {
+            i = i + 1;
         }
+
+        let exponent_start = i;
+        i = skip_digits(bytes, i);
+
+        if i == exponent_start {
+            return None;
+        }
+    }
+
+    if i != bytes.len() {
+        return None;
+    }
+
+    // the shape is known to be valid, the conversion itself is left to the
+    // standard library which rounds correctly
+    let unsigned = if bytes[0] == b'+' { token.slice_from(1) } else { token };
+
+    return unsigned.parse::<GLfloat>();
+}
+
+
+/// Parses a single vertex reference in a face, which can take any of the
+/// forms `v`, `v/vt`, `v//vn` or `v/vt/vn`. Returns the 0-based vertex index,
+/// followed by the texture coordinate and normal indices, if any.
+fn parse_face_vertex(filepath: &str, line_num: usize, token: &str, num_vertices: usize, num_uvs: usize, num_normals: usize) -> Result<(GLuint, Option<GLuint>, Option<GLuint>), ImportError> {
+    let parts: Vec<&str> = token.split('/').collect();
+
+    if parts.len() > 3 || parts[0].is_empty() {
+        return Err(ImportError::new(filepath, line_num, ImportErrorKind::MalformedLine(token.to_string())));
+    }
+
+    let vertex_index = try!(parse_index(filepath, line_num, parts[0], num_vertices));
+
+    let uv_index = if parts.len() > 1 && !parts[1].is_empty() {
+        Some(try!(parse_index(filepath, line_num, parts[1], num_uvs)))
+    } else {
+        None
+    };
+
+    let normal_index = if parts.len() > 2 && !parts[2].is_empty() {
+        Some(try!(parse_index(filepath, line_num, parts[2], num_normals)))
+    } else {
+        None
+    };
+
+    return Ok((vertex_index, uv_index, normal_index));
+}
+
+
+/// Parses an OBJ index into a 0-based index, checking that it refers to one
+/// of the `count` elements defined so far. Positive indices are 1-based and
+/// negative indices are relative to the end of the elements defined so far.
+fn parse_index(filepath: &str, line_num: usize, token: &str, count: usize) -> Result<GLuint, ImportError> {
+    let index = match token.parse::<i64>() {
+        Some(index) => index,
+        None => return Err(ImportError::new(filepath, line_num, ImportErrorKind::BadNumber(token.to_string()))),
+    };
+
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ImportError::new(filepath, line_num, ImportErrorKind::IndexOutOfRange(index)));
+    }
+
+    return Ok(resolved as GLuint);
+}
+
+
+/// Assigns a single index to every distinct corner, returning the distinct
+/// corners in order of first occurrence along with the index of each corner.
+/// Runs in linear time in the number of corners.
+fn unify_corners<K: Hash + Eq + Clone>(corners: &Vec<K>) -> (Vec<K>, Vec<u32>) {
+    let mut unified: HashMap<K, u32> = HashMap::with_capacity(corners.len());
+    let mut unique_corners: Vec<K> = Vec::new();
+    let mut new_indices: Vec<u32> = Vec::with_capacity(corners.len());
+
+    for corner in corners.iter() {
+        let index = match unified.get(corner) {
+            Some(&index) => index,
+
+            None => {
+                // first occurrence
+                unique_corners.push(corner.clone());
+                unique_corners.len() as u32 - 1
+            }
+        };
+
+        unified.insert(corner.clone(), index);
+        new_indices.push(index);
     }
 
-    return (new_values_0, new_values_1, new_indices);
+    return (unique_corners, new_indices);
 }
 
+
 #[test]
-fn unify_indexes_test() {
+fn unify_corners_test() {
     let v1: Vec<i32> = vec!(1, 2, 3, 4, 5, 6);
     let v2: Vec<i32> = vec!(-1, -2, -3, -4, -5);
     let i1 = vec!(
@@ -117,7 +737,10 @@ fn unify_indexes_test() {
         2, 1, 0,
         2, 0, 1,
     );
-    let (v3, v4, i3) = unify_indexes(&i1, &v1, &i2, &v2);
+    let corners: Vec<(u32, u32)> = i1.iter().zip(i2.iter()).map(|(a, b)| (*a, *b)).collect();
+    let (unique_corners, i3) = unify_corners(&corners);
+    let v3: Vec<i32> = unique_corners.iter().map(|&(a, _)| v1[a as usize]).collect();
+    let v4: Vec<i32> = unique_corners.iter().map(|&(_, b)| v2[b as usize]).collect();
 
     // length must be preserved
     assert_eq!(i3.len(), 15);
@@ -159,3 +782,267 @@ fn unify_indexes_test() {
         assert_eq!(i4[i], i as u32);
     }
 }
+
+
+#[test]
+fn parse_face_vertex_test() {
+    // v
+    assert_eq!(parse_face_vertex("test.obj", 1, "3", 4, 0, 0).ok(), Some((2, None, None)));
+    // v/vt
+    assert_eq!(parse_face_vertex("test.obj", 1, "3/1", 4, 1, 0).ok(), Some((2, Some(0), None)));
+    // v//vn
+    assert_eq!(parse_face_vertex("test.obj", 1, "3//2", 4, 0, 2).ok(), Some((2, None, Some(1))));
+    // v/vt/vn
+    assert_eq!(parse_face_vertex("test.obj", 1, "3/1/2", 4, 1, 2).ok(), Some((2, Some(0), Some(1))));
+    // relative indices
+    assert_eq!(parse_face_vertex("test.obj", 1, "-1//-2", 4, 0, 2).ok(), Some((3, None, Some(0))));
+
+    // out of range
+    assert!(parse_face_vertex("test.obj", 1, "0", 4, 0, 0).is_err());
+    assert!(parse_face_vertex("test.obj", 1, "5", 4, 0, 0).is_err());
+    assert!(parse_face_vertex("test.obj", 1, "-5", 4, 0, 0).is_err());
+    assert!(parse_face_vertex("test.obj", 1, "1/2", 4, 1, 0).is_err());
+
+    // malformed
+    assert!(parse_face_vertex("test.obj", 1, "/1/1", 4, 1, 1).is_err());
+    assert!(parse_face_vertex("test.obj", 1, "1/1/1/1", 4, 1, 1).is_err());
+    assert!(parse_face_vertex("test.obj", 1, "a", 4, 0, 0).is_err());
+}
+
+
+#[test]
+fn parse_float_test() {
+    assert_eq!(parse_float("1"), Some(1.0));
+    assert_eq!(parse_float("-2"), Some(-2.0));
+    assert_eq!(parse_float("+3.25"), Some(3.25));
+    assert_eq!(parse_float(".5"), Some(0.5));
+    assert_eq!(parse_float("-.5"), Some(-0.5));
+    assert_eq!(parse_float("2."), Some(2.0));
+    assert_eq!(parse_float("1e-3"), Some(0.001));
+    assert_eq!(parse_float("1.5E+2"), Some(150.0));
+    assert_eq!(parse_float("0.000000"), Some(0.0));
+    assert_eq!(parse_float("-0.943500"), Some(-0.9435));
+    assert_eq!(parse_float("0.1000000000000000055511151231257827021181583404541015625"), Some(0.1));
+    assert_eq!(parse_float("123456789012345678901234567890"), Some(1.2345679e29));
+
+    assert_eq!(parse_float(""), None);
+    assert_eq!(parse_float("."), None);
+    assert_eq!(parse_float("-"), None);
+    assert_eq!(parse_float("e5"), None);
+    assert_eq!(parse_float("1e"), None);
+    assert_eq!(parse_float("1.0.0"), None);
+    assert_eq!(parse_float("+-1"), None);
+    assert_eq!(parse_float("1,0"), None);
+}
+
+
+#[test]
+fn import_obj_from_reader_test() {
+    use std::io::BufReader;
+
+    let source = b"
+        # a unit quad and a triangle without normals
+        o Quad
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vn 0 0 1
+        f 1//1 2//1 3//1 4//1
+
+        # lines and free-form geometry are skipped
+        l 1 2 3
+        cstype bspline
+        deg 3
+        curv 0.0 1.0 1 2 3 4
+        end
+        mg 1 0.5
+        lod 1
+
+        g Triangle
+        f -4 -3 -2
+    ";
+
+    let mesh = import_obj_from_reader(&mut BufReader::new(source), "memory", None, &Default::default()).ok().unwrap();
+
+    // quads are split into two triangles
+    assert_eq!(mesh.indices.len(), 9);
+    assert_eq!(mesh.vertices.len(), mesh.normals.len());
+    assert!(mesh.uvs.is_empty());
+
+    assert_eq!(mesh.parts.len(), 2);
+    assert_eq!(mesh.parts[0].name, Some("Quad".to_string()));
+    assert_eq!((mesh.parts[0].offset, mesh.parts[0].count), (0, 6));
+    assert_eq!(mesh.parts[1].name, Some("Triangle".to_string()));
+    assert_eq!((mesh.parts[1].offset, mesh.parts[1].count), (6, 3));
+}
+
+
+#[test]
+fn import_obj_parts_test() {
+    use std::io::BufReader;
+
+    // a ramp and a ball authored away from the origin of the file, with the
+    // ramp split in two by a material and continued after the ball
+    let source = b"
+        v 0 0 0
+        v 4 0 0
+        v 4 2 0
+        v 0 2 -2
+        v 9 1 1
+        v 11 1 1
+        v 10 3 1
+
+        o Ramp
+        f 1 2 3
+        usemtl Stone
+        f 1 3 4
+        g Ball
+        f 5 6 7
+        o Ramp
+        f 2 3 4
+    ";
+
+    let mesh = import_obj_from_reader(&mut BufReader::new(source), "memory", None, &Default::default()).ok().unwrap();
+
+    let parts: Vec<(Option<&str>, usize, usize, [f32; 3])> = mesh.parts.iter()
+        .map(|part| (part.name.as_ref().map(|name| name.as_slice()), part.offset, part.count, part.origin))
+        .collect();
+
+    assert_eq!(parts, vec![
+        (Some("Ramp"), 0, 3, [2.0, 1.0, -1.0]),
+        (Some("Ramp"), 3, 3, [2.0, 1.0, -1.0]),
+        (Some("Ball"), 6, 3, [10.0, 2.0, 1.0]),
+        (Some("Ramp"), 9, 3, [2.0, 1.0, -1.0]),
+    ]);
+}
+
+
+#[test]
+fn import_obj_from_reader_without_materials_test() {
+    use std::io::BufReader;
+
+    // exporters always reference a material library, which can not be loaded
+    // without a base directory
+    let source = b"
+        mtllib scene.mtl
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        usemtl Red
+        f 1 2 3
+        usemtl Blue
+        f 3 2 1
+    ";
+
+    let mesh = import_obj_from_reader(&mut BufReader::new(source), "memory", None, &Default::default()).ok().unwrap();
+
+    assert_eq!(mesh.parts.len(), 2);
+    assert!(mesh.parts.iter().all(|part| part.material.is_none()));
+    assert_eq!((mesh.parts[0].offset, mesh.parts[0].count), (0, 3));
+    assert_eq!((mesh.parts[1].offset, mesh.parts[1].count), (3, 3));
+}
+
+
+#[test]
+fn import_obj_from_reader_error_test() {
+    use std::io::BufReader;
+
+    fn error_of(source: &[u8]) -> (usize, String) {
+        match import_obj_from_reader(&mut BufReader::new(source), "memory", None, &Default::default()) {
+            Ok(_) => panic!("expected the import to fail"),
+            Err(err) => (err.line, err.description().to_string()),
+        }
+    }
+
+    assert_eq!(error_of(b"v 0 0 0\nv 1 0 x\n"), (2, "bad number".to_string()));
+    assert_eq!(error_of(b"v 0 0 0\nf 1 2 3\n"), (2, "index out of range".to_string()));
+    assert_eq!(error_of(b"\n\nvertex 0 1 1\n"), (3, "unsupported directive".to_string()));
+    assert_eq!(error_of(b"v 0 0\n"), (1, "malformed line".to_string()));
+}
+
+
+#[test]
+fn import_mtl_from_reader_test() {
+    use std::io::BufReader;
+
+    let source = b"
+        # a translucent textured material and one without any properties
+        newmtl Glass
+        Kd 0.1 0.2 0.3
+        Ks 0.5 0.5 0.5
+        Ns 64
+        d 0.25
+        illum 2
+        map_Kd textures/glass.png
+
+        newmtl Plain
+    ";
+
+    let materials = import_mtl_from_reader(&mut BufReader::new(source), "memory", &Path::new("models")).ok().unwrap();
+    assert_eq!(materials.len(), 2);
+
+    let glass = &materials["Glass".to_string()];
+    assert_eq!(glass.diffuse, [0.1, 0.2, 0.3]);
+    assert_eq!(glass.specular, [0.5; 3]);
+    assert_eq!(glass.shininess, 64.0);
+    assert_eq!(glass.opacity, 0.25);
+    assert_eq!(glass.diffuse_map, Some("models/textures/glass.png".to_string()));
+
+    let plain = &materials["Plain".to_string()];
+    assert_eq!(plain.diffuse, DEFAULT_DIFFUSE);
+    assert_eq!(plain.opacity, 1.0);
+    assert_eq!(plain.diffuse_map, None);
+}
+
+
+#[test]
+fn import_obj_materials_test() {
+    use std::io::TempDir;
+
+    let directory = TempDir::new("mithril-examples").unwrap();
+
+    File::create(&directory.path().join("scene.mtl")).write(b"
+        newmtl Red
+        Kd 1 0 0
+        newmtl Blue
+        Kd 0 0 1
+    ").unwrap();
+
+    File::create(&directory.path().join("scene.obj")).write(b"
+        mtllib scene.mtl missing.mtl
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+
+        usemtl Red
+        f 1 2 3 4
+        usemtl Blue
+        f 1 2 3
+        usemtl Red
+        f 1 3 4
+        usemtl Undefined
+        f 2 3 4
+    ").unwrap();
+
+    let filepath = directory.path().join("scene.obj");
+    let mesh = import_from_obj(filepath.as_str().unwrap(), &Default::default()).ok().unwrap();
+
+    let parts: Vec<(Option<[f32; 3]>, usize, usize)> = mesh.parts.iter()
+        .map(|part| (part.material.as_ref().map(|material| material.diffuse), part.offset, part.count))
+        .collect();
+
+    let libraries: Vec<String> = ["scene.mtl", "missing.mtl"].iter()
+        .map(|name| directory.path().join(*name).as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(mesh.dependencies(), libraries);
+
+    // the missing library and the undefined material do not stop the import
+    assert_eq!(parts, vec![
+        (Some([1.0, 0.0, 0.0]), 0, 6),
+        (Some([0.0, 0.0, 1.0]), 6, 3),
+        (Some([1.0, 0.0, 0.0]), 9, 3),
+        (None, 12, 3),
+    ]);
+}
@@ -3,79 +3,311 @@
 
 extern crate gl;
 extern crate glfw;
+extern crate mithril;
 
 use glfw::{Action, Context, Glfw, Key, Window, WindowEvent};
 use graphics::GraphicsEngine;
-use std::io;
+use mithril::core::{ Body, State };
+use mithril::dynamics::World;
+use mithril::materials::Rigid;
+use mithril::shapes::{ Cube, Sphere };
+use simulation::Simulation;
+use std::default::Default;
+use std::os;
 use std::time;
 use std::sync;
 
 mod graphics;
+mod simulation;
+#[cfg(test)]
+mod regression;
+
+/// Bodies falling below this height have left the world and are despawned.
+const KILL_PLANE_HEIGHT: f32 = -50.0;
+
+const USAGE: &'static str = "Usage: mithril-examples [--headless [--steps N] [--screenshot FILE]]
+
+Options:
+    --headless          Simulate without showing a window, then render the
+                        final state offscreen
+    --steps N           The number of time steps to simulate when headless,
+                        100 by default
+    --screenshot FILE   Saves the final state to a PNG file when headless";
+
+/// The command line options, see `USAGE`.
+struct Options {
+    headless: bool,
+    steps: usize,
+    screenshot: Option<String>,
+}
 
 fn main() {
-    let mut app = Application::new();
+    let options = match parse_options(os::args().tail()) {
+        Ok(options) => options,
+
+        Err(message) => {
+            println!("{}\n\n{}", message, USAGE);
+            os::set_exit_status(1);
+            return;
+        }
+    };
+
+    let mut app = Application::new(!options.headless);
+    let time_step = time::Duration::milliseconds(10);
+
+    if !options.headless {
+        app.run(time_step);
+        return;
+    }
+
+    match app.run_headless(time_step, options.steps) {
+        Ok(screenshot) => {
+            match options.screenshot {
+                Some(ref filepath) => {
+                    match screenshot.save_png(filepath.as_slice()) {
+                        Ok(()) => println!("Saved screenshot to {}", filepath),
+
+                        Err(err) => {
+                            println!("Failed to save screenshot: {}", err);
+                            os::set_exit_status(1);
+                        }
+                    }
+                }
+
+                None => { /* do nothing */ }
+            }
+        }
+
+        Err(err) => {
+            println!("Failed to render offscreen: {}", err);
+            os::set_exit_status(1);
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options{ headless: false, steps: 100, screenshot: None };
+    let mut iter = args.iter();
 
-    app.run(time::Duration::milliseconds(17));
+    loop {
+        let arg = match iter.next() {
+            Some(arg) => arg,
+            None => break,
+        };
+
+        match arg.as_slice() {
+            "--headless" => options.headless = true,
+
+            "--steps" => {
+                options.steps = match iter.next().and_then(|value| value.parse()) {
+                    Some(steps) => steps,
+                    None => return Err("--steps expects a number".to_string()),
+                };
+            }
+
+            "--screenshot" => {
+                options.screenshot = match iter.next() {
+                    Some(filepath) => Some(filepath.clone()),
+                    None => return Err("--screenshot expects a file name".to_string()),
+                };
+            }
+
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+
+    if !options.headless && options.screenshot.is_some() {
+        return Err("--screenshot requires --headless".to_string());
+    }
+
+    return Ok(options);
 }
 
 pub struct Application<'a> {
     context: glfw::Glfw,
     window: glfw::Window,
     graphics: GraphicsEngine<'a>,
+    simulation: Simulation,
     events_receiver: sync::mpsc::Receiver<(f64, glfw::WindowEvent)>,
-    timer: io::Timer,
     left_mouse_button_down: bool,
 }
 
 impl<'a> Application<'a> {
-    fn new() -> Application<'a> {
+    /// Creates the application and its window, which stays hidden unless
+    /// `visible` is set.
+    fn new(visible: bool) -> Application<'a> {
         let context = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
 
         context.window_hint(glfw::WindowHint::ContextVersion(3, 2));
         context.window_hint(glfw::WindowHint::OpenglForwardCompat(true));
         context.window_hint(glfw::WindowHint::OpenglProfile(glfw::OpenGlProfileHint::Core));
+        // lets the driver explain errors through the debug output
+        context.window_hint(glfw::WindowHint::OpenglDebugContext(true));
+        context.window_hint(glfw::WindowHint::Visible(visible));
+
+        match os::getenv("MITHRIL_GL_ERRORS") {
+            Some(ref mode) if mode.as_slice() == "log" => graphics::gl_error::set_error_mode(graphics::ErrorMode::Log),
+            _ => { /* do nothing */ }
+        }
 
         let (window, events) = context.create_window(640, 480, "mithril - testbed", glfw::WindowMode::Windowed)
             .expect("Failed to create GLFW window");
 
         window.set_all_polling(true);
         window.make_current();
+        context.set_swap_interval(1);
 
         return Application{
             context: context,
             graphics: GraphicsEngine::new(&window),
             window: window,
-            timer: io::Timer::new().unwrap(),
+            simulation: Simulation::new(World::new(), 0.01),
             events_receiver: events,
             left_mouse_button_down: false,
         };
     }
 
-    fn run(&mut self, duration: time::Duration) {
-        let period = self.timer.periodic(duration);
+    /// Runs the application until the window is closed, stepping the physics
+    /// world at a fixed `time_step` regardless of the frame rate.
+    fn run(&mut self, time_step: time::Duration) {
+        self.simulation.set_time_step(time_step.num_milliseconds() as f32 / 1000.0);
 
-        {
-            let asset_ref = self.graphics.new_asset_from_file("assets/cube.obj");
-            self.graphics.create_object_from_asset(asset_ref.clone());
-            let obj = self.graphics.create_object_from_asset(asset_ref.clone());
-            obj.set_translation(-3.0, -1.0, -1.0);
-        }
+        self.load_scene();
 
-        {
-            let asset_ref = self.graphics.new_asset_from_file("assets/isosphere.obj");
-            let obj = self.graphics.create_object_from_asset(asset_ref.clone());
-            obj.set_translation(3.0, 2.0, -1.0);
-        }
+        let mut previous_time = self.context.get_time();
 
         while !self.window.should_close() {
             self.context.poll_events();
             self.flush_events_queue();
 
+            let current_time = self.context.get_time();
+            self.graphics.reload_changed_assets(current_time);
+            self.graphics.reload_changed_shaders(current_time);
+
+            self.simulation.accumulate((current_time - previous_time) as f32);
+            previous_time = current_time;
+
+            while self.simulation.step_pending() {
+                self.sync_objects_with_bodies();
+            }
+
             self.graphics.camera_mut().update();
-            self.graphics.draw();
+            self.graphics.draw(self.simulation.interpolation_factor());
 
             self.window.swap_buffers();
-            period.recv().unwrap();
+        }
+    }
+
+    /// Simulates `steps` time steps as fast as possible, then renders the final
+    /// state into an offscreen framebuffer and reads it back. Does not need the
+    /// window to be visible.
+    fn run_headless(&mut self, time_step: time::Duration, steps: usize) -> Result<graphics::Screenshot, String> {
+        self.simulation.set_time_step(time_step.num_milliseconds() as f32 / 1000.0);
+
+        self.load_scene();
+
+        for _ in range(0, steps) {
+            self.simulation.step();
+            self.sync_objects_with_bodies();
+        }
+
+        let (width, height) = self.window.get_framebuffer_size();
+        let framebuffer = try!(graphics::Framebuffer::new(width as u32, height as u32));
+
+        framebuffer.bind();
+        self.graphics.draw(1.0);
+        framebuffer.unbind();
+
+        return Ok(framebuffer.read_pixels());
+    }
+
+    /// Populates the graphics engine and the simulation with the demo scene.
+    fn load_scene(&mut self) {
+        match self.graphics.new_asset_from_file("assets/plane.obj") {
+            Ok(asset_ref) => {
+                let texture = match self.graphics.new_texture_from_file("assets/checkerboard.png") {
+                    Ok(texture_ref) => Some(texture_ref),
+
+                    Err(err) => {
+                        println!("Failed to load texture: {:?}", err);
+                        None
+                    }
+                };
+                let handle = self.graphics.create_object_from_asset(asset_ref.clone());
+                let obj = self.graphics.object_mut(handle).unwrap();
+                obj.set_translation(0.0, -4.0, 0.0);
+                obj.set_texture(texture);
+            }
+
+            Err(err) => println!("Failed to load asset: {}", err),
+        }
+
+        match self.graphics.new_asset_from_file("assets/cube.obj") {
+            Ok(asset_ref) => {
+                self.graphics.create_object_from_asset(asset_ref.clone());
+
+                let body_id = self.simulation.add_body(Body::new_with_id(1, Box::new(Cube::new(2.0, 2.0, 2.0)), Box::new(Rigid::new(1.0)), State::new_with_position(-3.0, -1.0, -1.0)));
+                let handle = self.graphics.create_object_from_asset(asset_ref.clone());
+                let obj = self.graphics.object_mut(handle).unwrap();
+                obj.set_translation(-3.0, -1.0, -1.0);
+                obj.set_color(0.2, 0.4, 1.0);
+                obj.bind_to_body(body_id);
+            }
+
+            Err(err) => println!("Failed to load asset: {}", err),
+        }
+
+        match self.graphics.new_asset_from_file("assets/isosphere.obj") {
+            Ok(asset_ref) => {
+                let body_id = self.simulation.add_body(Body::new_with_id(2, Box::new(Sphere::new(1.0)), Box::new(Rigid::new(1.0)), State::new_with_position(3.0, 2.0, -1.0)));
+                let handle = self.graphics.create_object_from_asset(asset_ref.clone());
+                let obj = self.graphics.object_mut(handle).unwrap();
+                obj.set_translation(3.0, 2.0, -1.0);
+                obj.set_material(Some(graphics::Material{
+                    diffuse: [1.0, 0.8, 0.1],
+                    specular: [0.6; 3],
+                    shininess: 32.0,
+                    .. Default::default()
+                }));
+                obj.bind_to_body(body_id);
+            }
+
+            Err(err) => println!("Failed to load asset: {}", err),
+        }
+    }
+
+    /// Clears the scene and the simulation, then loads the scene from scratch.
+    fn reset_scene(&mut self) {
+        self.graphics.remove_all_objects();
+        self.simulation = Simulation::new(World::new(), self.simulation.time_step());
+        self.load_scene();
+    }
+
+    fn sync_objects_with_bodies(&mut self) {
+        let mut despawned = Vec::new();
+
+        for (handle, object) in self.graphics.objects_mut() {
+            match object.body_id() {
+                Some(body_id) => {
+                    match self.simulation.body_transform(body_id) {
+                        Some((position, _)) if position[1] < KILL_PLANE_HEIGHT => {
+                            despawned.push((handle, body_id));
+                        }
+
+                        Some((position, rotation)) => object.set_body_transform(position, rotation),
+
+                        // the body no longer exists
+                        None => despawned.push((handle, body_id)),
+                    }
+                }
+
+                None => { /* do nothing */ }
+            }
+        }
+
+        for &(handle, body_id) in despawned.iter() {
+            self.graphics.remove_object(handle);
+            self.simulation.remove_body(body_id);
         }
     }
 
@@ -86,6 +318,31 @@ impl<'a> Application<'a> {
                     self.window.set_should_close(true);
                 }
 
+                glfw::WindowEvent::Key(Key::R, _, Action::Press, _) => {
+                    self.reset_scene();
+                    println!("Scene reset");
+                }
+
+                glfw::WindowEvent::Key(Key::Space, _, Action::Press, _) => {
+                    self.simulation.toggle_pause();
+                    println!("Simulation {}", if self.simulation.is_paused() { "paused" } else { "resumed" });
+                }
+
+                glfw::WindowEvent::Key(Key::Period, _, Action::Press, _) |
+                glfw::WindowEvent::Key(Key::Period, _, Action::Repeat, _) => {
+                    self.simulation.request_single_step();
+                }
+
+                glfw::WindowEvent::Key(Key::Equal, _, Action::Press, _) => {
+                    self.simulation.scale_speed(2.0);
+                    println!("Simulation speed: {}x", self.simulation.speed());
+                }
+
+                glfw::WindowEvent::Key(Key::Minus, _, Action::Press, _) => {
+                    self.simulation.scale_speed(0.5);
+                    println!("Simulation speed: {}x", self.simulation.speed());
+                }
+
                 glfw::WindowEvent::Scroll(_, y) => {
                     let mut camera = self.graphics.camera_mut();
                     let new_pos = (camera.position() - camera.focus_point()) * (1.0 + y as f32) + camera.focus_point();
@@ -0,0 +1,148 @@
+//! Golden-image regression tests for the renderer. Known scenes are rendered
+//! offscreen and compared against reference images in `tests/golden`.
+//!
+//! The rendering tests need a display and an OpenGL 3.2 context, so they are
+//! ignored by default. Run them with `./regression.sh`, which sets up Mesa's
+//! software rasterizer and runs them on a single thread as GLFW requires, or
+//! `./regression.sh --update` to record missing references or replace them
+//! after an intended change to the renderer.
+
+extern crate image;
+extern crate mithril;
+
+use Application;
+use graphics::Screenshot;
+use std::io::fs;
+use std::os;
+use std::time;
+use self::mithril::math::Vector;
+
+/// The largest difference allowed in any channel of a pixel, absorbs
+/// rounding differences between drivers.
+const CHANNEL_TOLERANCE: u8 = 8;
+
+/// The fraction of pixels allowed to exceed the channel tolerance, absorbs
+/// rasterization differences along edges.
+const MAX_MISMATCHED_FRACTION: f32 = 0.002;
+
+const GOLDEN_DIRECTORY: &'static str = "tests/golden";
+const OUTPUT_DIRECTORY: &'static str = "target/regression";
+
+/// The outcome of comparing an image against its reference.
+struct Comparison {
+    mismatched_pixels: usize,
+    total_pixels: usize,
+    diff: Screenshot,
+}
+
+/// Compares the images pixel by pixel. The diff image shows mismatched pixels
+/// in red over a faded copy of the reference.
+fn compare(actual: &Screenshot, reference: &Screenshot) -> Comparison {
+    let mut mismatched_pixels = 0;
+    let mut diff_pixels = Vec::with_capacity(reference.pixels.len());
+
+    for (actual_pixel, reference_pixel) in actual.pixels.chunks(4).zip(reference.pixels.chunks(4)) {
+        let matches = actual_pixel.iter().zip(reference_pixel.iter()).all(|(&a, &b)| {
+            (if a > b { a - b } else { b - a }) <= CHANNEL_TOLERANCE
+        });
+
+        if matches {
+            let luminance = (reference_pixel[0] as u32 + reference_pixel[1] as u32 + reference_pixel[2] as u32) / 3;
+            let faded = (128 + luminance / 2) as u8;
+            diff_pixels.push_all(&[faded, faded, faded, 255]);
+        } else {
+            mismatched_pixels = mismatched_pixels + 1;
+            diff_pixels.push_all(&[255, 0, 0, 255]);
+        }
+    }
+
+    return Comparison{
+        mismatched_pixels: mismatched_pixels,
+        total_pixels: (reference.width * reference.height) as usize,
+        diff: Screenshot{ width: reference.width, height: reference.height, pixels: diff_pixels },
+    };
+}
+
+
+fn load_reference(filepath: &Path) -> Option<Screenshot> {
+    match image::open(filepath) {
+        Ok(image) => {
+            let pixels = image.to_rgba();
+            let (width, height) = pixels.dimensions();
+
+            Some(Screenshot{ width: width, height: height, pixels: pixels.into_vec() })
+        }
+
+        Err(_) => None,
+    }
+}
+
+
+/// Checks the screenshot against the reference image named `name`, writing
+/// the actual and diff images to `target/regression` on failure.
+fn assert_matches_golden(name: &str, actual: &Screenshot) {
+    let reference_path = Path::new(GOLDEN_DIRECTORY).join(format!("{}.png", name));
+    let update = os::getenv("UPDATE_GOLDEN").map(|value| value.as_slice() == "1").unwrap_or(false);
+
+    if update {
+        fs::mkdir_recursive(&Path::new(GOLDEN_DIRECTORY), ::std::io::USER_RWX).unwrap();
+        actual.save_png(reference_path.as_str().unwrap()).unwrap();
+        println!("Recorded reference image {}", reference_path.display());
+        return;
+    }
+
+    let reference = match load_reference(&reference_path) {
+        Some(reference) => reference,
+        None => panic!("Missing reference image {}, run the tests with UPDATE_GOLDEN=1 to record it", reference_path.display()),
+    };
+
+    if (actual.width, actual.height) != (reference.width, reference.height) {
+        panic!("{} is {}x{} but its reference is {}x{}", name, actual.width, actual.height, reference.width, reference.height);
+    }
+
+    let comparison = compare(actual, &reference);
+    let mismatched_fraction = comparison.mismatched_pixels as f32 / comparison.total_pixels as f32;
+
+    if mismatched_fraction > MAX_MISMATCHED_FRACTION {
+        let output_directory = Path::new(OUTPUT_DIRECTORY);
+        let actual_path = output_directory.join(format!("{}.actual.png", name));
+        let diff_path = output_directory.join(format!("{}.diff.png", name));
+
+        fs::mkdir_recursive(&output_directory, ::std::io::USER_RWX).unwrap();
+        actual.save_png(actual_path.as_str().unwrap()).unwrap();
+        comparison.diff.save_png(diff_path.as_str().unwrap()).unwrap();
+
+        panic!("{} differs from its reference in {} of {} pixels, see {} and {}",
+               name, comparison.mismatched_pixels, comparison.total_pixels, actual_path.display(), diff_path.display());
+    }
+}
+
+
+#[test]
+fn compare_test() {
+    let reference = Screenshot{ width: 2, height: 1, pixels: vec![10, 20, 30, 255, 0, 0, 0, 255] };
+    let actual = Screenshot{ width: 2, height: 1, pixels: vec![14, 16, 30, 255, 0, 0, 200, 255] };
+
+    let comparison = compare(&actual, &reference);
+
+    assert_eq!(comparison.mismatched_pixels, 1);
+    assert_eq!(comparison.total_pixels, 2);
+    assert_eq!(comparison.diff.pixel(0, 0), [138, 138, 138, 255]);
+    assert_eq!(comparison.diff.pixel(1, 0), [255, 0, 0, 255]);
+}
+
+
+#[test]
+#[ignore]
+fn scene_regression_test() {
+    let mut app = Application::new(false);
+    app.graphics.camera_mut().go_to(Vector::new(6.0, 4.0, 8.0));
+
+    // the simulation is deterministic when stepped without a clock
+    let screenshot = match app.run_headless(time::Duration::milliseconds(10), 50) {
+        Ok(screenshot) => screenshot,
+        Err(err) => panic!("Failed to render offscreen: {}", err),
+    };
+
+    assert_matches_golden("scene_50_steps", &screenshot);
+}
@@ -0,0 +1,152 @@
+extern crate mithril;
+
+use self::mithril::core::Body;
+use self::mithril::dynamics::World;
+use std::num::Float;
+use self::mithril::math::{ Vector, Quaternion };
+
+pub type BodyId = usize;
+
+/// The largest amount of time the simulation will try to catch up on in a
+/// single frame, prevents long stalls from triggering an endless backlog.
+const MAX_ACCUMULATED_TIME: f32 = 0.25;
+
+const MIN_SPEED: f32 = 1.0 / 64.0;
+const MAX_SPEED: f32 = 8.0;
+
+pub struct Simulation {
+    world: World,
+    time_step: f32,
+    accumulator: f32,
+    speed: f32,
+    paused: bool,
+    single_step_requested: bool,
+}
+
+impl Simulation {
+    pub fn new(world: World, time_step: f32) -> Simulation {
+        Simulation{
+            world: world,
+            time_step: time_step,
+            accumulator: 0.0,
+            speed: 1.0,
+            paused: false,
+            single_step_requested: false,
+        }
+    }
+
+
+    pub fn add_body(&mut self, body: Body) -> BodyId {
+        let id = body.id();
+        self.world.add_body(body);
+
+        return id;
+    }
+
+
+    pub fn remove_body(&mut self, id: BodyId) {
+        self.world.remove_body(id);
+    }
+
+
+    #[inline]
+    pub fn time_step(&self) -> f32 {
+        self.time_step
+    }
+
+
+    #[inline]
+    pub fn set_time_step(&mut self, time_step: f32) {
+        self.time_step = time_step;
+    }
+
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+
+    #[inline]
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+
+    /// Requests exactly one time step to be taken while the simulation is
+    /// paused. Has no effect if the simulation is running.
+    pub fn request_single_step(&mut self) {
+        if self.paused {
+            self.single_step_requested = true;
+        }
+    }
+
+
+    #[inline]
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+
+    /// Multiplies the rate at which simulated time passes relative to
+    /// wall-clock time, the time step itself is unaffected.
+    pub fn scale_speed(&mut self, factor: f32) {
+        self.speed = (self.speed * factor).max(MIN_SPEED).min(MAX_SPEED);
+    }
+
+
+    /// Adds elapsed wall-clock time to the pool of time to be simulated.
+    pub fn accumulate(&mut self, elapsed: f32) {
+        if !self.paused {
+            self.accumulator = (self.accumulator + elapsed * self.speed).min(MAX_ACCUMULATED_TIME);
+        }
+    }
+
+
+    /// Advances the world by a single fixed time step if enough time has
+    /// accumulated, returns true if a step was taken.
+    pub fn step_pending(&mut self) -> bool {
+        if self.single_step_requested {
+            self.single_step_requested = false;
+            self.world.update(self.time_step);
+
+            return true;
+        }
+
+        if !self.paused && self.accumulator >= self.time_step {
+            self.world.update(self.time_step);
+            self.accumulator = self.accumulator - self.time_step;
+
+            return true;
+        }
+
+        return false;
+    }
+
+
+    /// Advances the world by a single time step regardless of the elapsed
+    /// time, for running without a clock.
+    pub fn step(&mut self) {
+        self.world.update(self.time_step);
+    }
+
+
+    /// The fraction of a time step left over in the accumulator, used to
+    /// interpolate between the last two simulated states when rendering. While
+    /// paused the latest state is shown as is.
+    #[inline]
+    pub fn interpolation_factor(&self) -> f32 {
+        if self.paused {
+            1.0
+        } else {
+            self.accumulator / self.time_step
+        }
+    }
+
+
+    /// Returns the simulated position and orientation of the body, or `None`
+    /// if no body with the given id exists in the world.
+    pub fn body_transform(&self, id: BodyId) -> Option<(Vector, Quaternion)> {
+        self.world.find_body(id).map(|body| (body.position(), body.rotation_quaternion()))
+    }
+}